@@ -1,24 +1,106 @@
 pub mod accd;
 pub mod closest_point;
+mod collider;
 pub mod distance_squared;
+pub mod distance_type;
 pub mod intersecting;
 mod segment;
 pub mod smallest_offset;
 mod triangle_face;
 mod vertex;
-mod collider;
 /*
 #[cfg(test)]
 mod tests;
 */
+use crate::collision_detection::accd::{
+    accd, PointTriangle3D, SegmentSegment3D, DEFAULT_DELTA_SCALE, DEFAULT_SCALE,
+};
+use crate::spatial::{Object, SpatialDB};
 pub use segment::Segment;
 pub use triangle_face::TriangleFace;
 pub use vertex::Vertex;
-use crate::spatial::SpatialDB;
 
-pub fn minimum_time_step<RF: RealField, const D: usize, Ctx: Clone, DB: SpatialDB<D, Ctx>>(time_step: RF, world: &DB, context: Ctx) -> RF {
-    for (a, b) in world.self_close_pairs(context.clone()) {
+/// A part of a collider that continuous collision detection is run on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Primitive<RF: RealField> {
+    Vertex(Vertex<RF, 3>),
+    Segment(Segment<RF, 3>),
+    Face(TriangleFace<RF, 3>),
+}
+
+/// Implemented by the items of a [`SpatialDB`] that are (or refer to) a [`Primitive`].
+pub trait AsPrimitive<Ctx>: Object<3, Ctx> {
+    fn as_primitive(&self, context: Ctx) -> Primitive<Self::RF>;
+}
 
-    }
-    todo!()
+/// The largest fraction of `time_step` that all the primitives in `world` can be advanced by,
+/// along their velocities, before any close pair of them comes within `barrier_thickness` of
+/// each other.  Only vertex-face and segment-segment pairs are checked, as those cover all the
+/// ways in which two meshes can collide.  Pairs whose swept bounding boxes are further apart than
+/// the barrier thickness are skipped.
+pub fn minimum_time_step<RF: RealField, Ctx: Clone, DB: SpatialDB<3, Ctx>>(
+    time_step: RF,
+    barrier_thickness: RF,
+    world: &DB,
+    context: Ctx,
+) -> RF
+where
+    DB::Item: AsPrimitive<Ctx, RF = RF>,
+{
+    let scale = RF::from_f64(DEFAULT_SCALE).unwrap();
+    let delta_scale = RF::from_f64(DEFAULT_DELTA_SCALE).unwrap();
+    let dhat2 = barrier_thickness.clone() * barrier_thickness.clone();
+    world
+        .self_close_pairs(context.clone())
+        .filter(|(a, b)| {
+            distance_squared::aabb_aabb(
+                &a.aabb_min(context.clone()),
+                &a.aabb_max(context.clone()),
+                &b.aabb_min(context.clone()),
+                &b.aabb_max(context.clone()),
+            ) <= dhat2
+        })
+        .filter_map(|(a, b)| {
+            match (
+                a.as_primitive(context.clone()),
+                b.as_primitive(context.clone()),
+            ) {
+                (Primitive::Vertex(p), Primitive::Face(t)) => {
+                    accd::<RF, 3, _, _, PointTriangle3D<RF>>(
+                        p,
+                        t,
+                        RF::zero(),
+                        scale.clone(),
+                        barrier_thickness.clone(),
+                        time_step.clone(),
+                        delta_scale.clone(),
+                    )
+                }
+                (Primitive::Face(t), Primitive::Vertex(p)) => {
+                    accd::<RF, 3, _, _, PointTriangle3D<RF>>(
+                        t,
+                        p,
+                        RF::zero(),
+                        scale.clone(),
+                        barrier_thickness.clone(),
+                        time_step.clone(),
+                        delta_scale.clone(),
+                    )
+                }
+                (Primitive::Segment(a), Primitive::Segment(b)) => {
+                    accd::<RF, 3, _, _, SegmentSegment3D<RF>>(
+                        a,
+                        b,
+                        RF::zero(),
+                        scale.clone(),
+                        barrier_thickness.clone(),
+                        time_step.clone(),
+                        delta_scale.clone(),
+                    )
+                }
+                _ => None,
+            }
+        })
+        .fold(time_step.clone(), |acc, t| acc.min(t))
+        / time_step
 }
@@ -34,6 +34,11 @@ pub trait D2Metric<N: RealField, const D: usize, T: ColliderPart<D, Scalar = N>,
 pub struct SegmentSegment3D<N: RealField>(PhantomData<fn() -> N>);
 pub struct PointTriangle3D<N: RealField>(PhantomData<fn() -> N>);
 
+/// The `scale` that the paper uses for [`accd`].
+pub const DEFAULT_SCALE: f64 = 0.1;
+/// The `delta_scale` that the paper uses for [`accd`].
+pub const DEFAULT_DELTA_SCALE: f64 = 0.9;
+
 pub struct IpcAccdContact<RF: RealField> {
     time: RF,
     distance: RF,
@@ -121,7 +126,8 @@ pub fn ipc_accd<
 ) -> Option<IpcAccdContact<RF>> {
     debug_assert!(RF::zero() < delta_scale);
     debug_assert!(delta_scale < RF::one());
-    let l_p = a.max_speed().max(b.max_speed());
+    //  Both parts move, so their relative speed is bounded by the sum of their speeds.
+    let l_p = a.max_speed() + b.max_speed();
     if l_p.is_zero() {
         return None;
     }
@@ -171,3 +177,31 @@ impl<N: RealField> D2Metric<N, 3, TriangleFace<N, 3>, Vertex<N, 3>> for PointTri
         distance_squared::point_triangle_3d(&b.x, &a.a.x, &a.b.x, &a.c.x)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::collision_detection::accd::{ipc_accd, PointTriangle3D};
+    use crate::collision_detection::{TriangleFace, Vertex};
+    use nalgebra::{point, Point3, Vector3};
+
+    #[test]
+    fn approaching_parts_do_not_pass_through() {
+        //  Both parts move at unit speed towards each other, so they meet at `t = 0.5`.  Bounding
+        //  their relative speed by the larger of the two speeds steps straight past that.
+        let vertex = |x: Point3<f64>, z: f64| Vertex {
+            x,
+            v: Vector3::new(0., 0., z),
+        };
+        let mut a = vertex(point![0., 0., 1.], -1.);
+        let mut b = TriangleFace {
+            a: vertex(point![-1., -1., 0.], 1.),
+            b: vertex(point![1., -1., 0.], 1.),
+            c: vertex(point![0., 1., 0.], 1.),
+        };
+        let contact =
+            ipc_accd::<_, 3, _, _, PointTriangle3D<f64>>(&mut a, &mut b, 0., 0.1, 0., 1., 0.9)
+                .unwrap();
+        assert!(contact.time < 0.5);
+        assert!(contact.distance < 0.1);
+    }
+}
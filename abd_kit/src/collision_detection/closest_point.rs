@@ -1,5 +1,3 @@
-use crate::collision_detection::smallest_offset;
-
 pub fn on_line<N: RealField, const D: usize>(
     p: &Point<N, D>,
     a: &Point<N, D>,
@@ -24,51 +22,142 @@ pub fn on_triangle_3d<N: RealField>(
     b: &Point3<N>,
     c: &Point3<N>,
 ) -> Point3<N> {
-    //  TODO:   use optimized code from
-    //          https://www.geometrictools.com/Documentation/DistancePoint3Triangle3.pdf
-    let t_ab = point_line_parameter(p, a, b);
-    let t_bc = point_line_parameter(p, b, c);
-    let t_ca = point_line_parameter(p, c, a);
-    if t_ca > N::one() && t_ab < N::zero() {
-        a.clone()
-    } else if t_ab > N::one() && t_bc < N::zero() {
-        b.clone()
-    } else if t_bc > N::one() && t_ca < N::zero() {
-        c.clone()
+    on_triangle_3d_barycentric(p, a, b, c).0
+}
+
+/// The closest point to `p` on the triangle `a, b, c`, together with its barycentric coordinates
+/// with respect to `a, b, c`.  The barycentric coordinates are always non-negative and sum to one.
+///
+/// This is the region based algorithm from
+/// [Distance Between Point and Triangle in 3D](https://www.geometrictools.com/Documentation/DistancePoint3Triangle3.pdf).
+/// Degenerate (sliver, collinear or coincident) triangles are handled by falling back to the
+/// closest point on their edges, which is off by at most the width of the triangle.
+pub fn on_triangle_3d_barycentric<N: RealField>(
+    p: &Point3<N>,
+    a: &Point3<N>,
+    b: &Point3<N>,
+    c: &Point3<N>,
+) -> (Point3<N>, [N; 3]) {
+    let (e0, e1, diff) = (b - a, c - a, a - p);
+    let (a00, a01, a11) = (e0.dot(&e0), e0.dot(&e1), e1.dot(&e1));
+    let (b0, b1) = (diff.dot(&e0), diff.dot(&e1));
+    let det = a00.clone() * a11.clone() - a01.clone() * a01.clone();
+    //  `det` is `|e0 × e1|²`, and loses all relative precision well before reaching zero.
+    if det <= N::default_epsilon().sqrt() * a00.clone() * a11.clone() {
+        return on_degenerate_triangle(p, a, b, c);
+    }
+    let (zero, one) = (N::zero(), N::one());
+    //  The minimizer of the distance along an edge of the triangle, clamped to [0, 1].
+    let clamped = |numer: N, denom: N| {
+        if numer <= N::zero() {
+            N::zero()
+        } else if numer >= denom {
+            N::one()
+        } else {
+            numer / denom
+        }
+    };
+    let mut s = a01.clone() * b1.clone() - a11.clone() * b0.clone();
+    let mut t = a01.clone() * b0.clone() - a00.clone() * b1.clone();
+    if s.clone() + t.clone() <= det {
+        if s < zero {
+            if t < zero && b0 < zero {
+                //  Region 4, closest to edge a b.
+                t = zero;
+                s = clamped(-b0, a00);
+            } else {
+                //  Regions 3 and 4, closest to edge c a.
+                s = zero;
+                t = clamped(-b1, a11);
+            }
+        } else if t < zero {
+            //  Region 5, closest to edge a b.
+            t = zero;
+            s = clamped(-b0, a00);
+        } else {
+            //  Region 0, closest to the interior.  Projecting onto the plane is more accurate
+            //  than recombining the vertices.
+            let normal = e0.cross(&e1);
+            let on = p - normal.scale((p - a).dot(&normal) / normal.magnitude_squared());
+            let (s, t) = (s / det.clone(), t / det);
+            let alpha = (N::one() - s.clone() - t.clone()).max(N::zero());
+            return (on, [alpha, s, t]);
+        }
     } else {
-        fn on_edge<N: RealField>(
-            t: N,
-            p: &Point3<N>,
-            a: &Point3<N>,
-            b: &Point3<N>,
-            c: &Point3<N>,
-        ) -> Option<Point3<N>> {
-            if (N::zero()..=N::one()).contains(&t) {
-                let on =
-                    Point3::from(a.coords.scale(N::one() - t.clone()) + b.coords.scale(t.clone()));
-                if (p - &on)
-                    .dot(&smallest_offset::point_to_line(c, a, b))
-                    .is_sign_positive()
-                {
-                    // panic!("{t}");
-                    Some(on)
-                } else {
-                    None
-                }
+        //  The parameter along edge b c, computed directly rather than from the cached dot
+        //  products, which suffer from cancellation.
+        let on_edge = || {
+            let edge = c - b;
+            clamped((p - b).dot(&edge), edge.magnitude_squared())
+        };
+        if s < zero {
+            //  Region 2, closest to edge b c or c a.
+            let (tmp0, tmp1) = (a01.clone() + b0.clone(), a11.clone() + b1.clone());
+            if tmp1 > tmp0 {
+                t = on_edge();
+                s = one - t.clone();
             } else {
-                None
+                s = zero;
+                t = clamped(-b1, a11);
             }
+        } else if t < zero {
+            //  Region 6, closest to edge b c or a b.
+            let (tmp0, tmp1) = (a01.clone() + b1.clone(), a00.clone() + b0.clone());
+            if tmp1 > tmp0 {
+                t = on_edge();
+                s = one - t.clone();
+            } else {
+                t = zero;
+                s = clamped(-b0, a00);
+            }
+        } else {
+            //  Region 1, closest to edge b c.
+            t = on_edge();
+            s = one - t.clone();
         }
-        if let Some(p) = on_edge(t_ab, p, &a, &b, &c)
-            .or_else(|| on_edge(t_bc, p, &b, &c, &a))
-            .or_else(|| on_edge(t_ca, p, &c, &a, &b))
-        {
-            return p;
+    }
+    let weights = [(N::one() - s.clone() - t.clone()).max(N::zero()), s, t];
+    (weighted(&weights, [a, b, c]), weights)
+}
+
+/// The closest point to `p` on the union of the edges of the triangle `a, b, c`.
+fn on_degenerate_triangle<N: RealField>(
+    p: &Point3<N>,
+    a: &Point3<N>,
+    b: &Point3<N>,
+    c: &Point3<N>,
+) -> (Point3<N>, [N; 3]) {
+    let vertices = [a, b, c];
+    let mut closest = None;
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        let (e0, e1) = (vertices[i], vertices[j]);
+        let length_squared = (e1 - e0).magnitude_squared();
+        let t = if length_squared > N::zero() {
+            ((p - e0).dot(&(e1 - e0)) / length_squared).clamp(N::zero(), N::one())
         } else {
-            let orth = (b - a).cross(&(c - a));
-            p - orth.scale((p - a).dot(&orth) / orth.magnitude_squared())
+            N::zero()
+        };
+        let mut weights = [N::zero(), N::zero(), N::zero()];
+        weights[i] = N::one() - t.clone();
+        weights[j] = t;
+        let on = weighted(&weights, vertices);
+        let distance_squared = (p - &on).magnitude_squared();
+        match &closest {
+            Some((d, _, _)) if *d <= distance_squared => {}
+            _ => closest = Some((distance_squared, on, weights)),
         }
     }
+    let (_, on, weights) = closest.unwrap();
+    (on, weights)
+}
+
+fn weighted<N: RealField>(weights: &[N; 3], vertices: [&Point3<N>; 3]) -> Point3<N> {
+    Point3::from(
+        vertices[0].coords.scale(weights[0].clone())
+            + vertices[1].coords.scale(weights[1].clone())
+            + vertices[2].coords.scale(weights[2].clone()),
+    )
 }
 
 pub fn point_line_parameter<N: RealField, const D: usize>(
@@ -79,250 +168,91 @@ pub fn point_line_parameter<N: RealField, const D: usize>(
     (p - a).dot(&(b - a)) / nalgebra::distance_squared(a, b)
 }
 
-/// When the lines are parallel, returns None.  Otherwise, returns the point parameter for line a.
+/// The parameters of the closest points on the segments `a0 a1` and `b0 b1`, both in `[0, 1]`.
+///
+/// This never fails: degenerate (zero length) and (nearly) parallel segments are handled by also
+/// considering the closest points along the boundary of the parameter square, which are exact
+/// for parallel segments, so the result degrades gracefully across the parallel threshold.  When
+/// the segments are parallel and overlapping, one of the (equally close) pairs is returned.
+pub fn segment_segment_parameters_3d<N: RealField>(
+    a0: &Point3<N>,
+    a1: &Point3<N>,
+    b0: &Point3<N>,
+    b1: &Point3<N>,
+) -> (N, N) {
+    let on_a =
+        |s: &N| Point3::from(a0.coords.scale(N::one() - s.clone()) + a1.coords.scale(s.clone()));
+    let on_b =
+        |t: &N| Point3::from(b0.coords.scale(N::one() - t.clone()) + b1.coords.scale(t.clone()));
+    //  The distance is convex in the parameters, so its minimum is either at the (clamped)
+    //  closest points of the lines, or somewhere along the boundary of the parameter square.
+    let mut candidates = Vec::with_capacity(5);
+    if let Some(s) = line_line_parameter_3d(a0, a1, b0, b1) {
+        //  One round of alternating projection keeps the pair consistent when the lines are
+        //  only just not parallel.
+        let t = on_segment_parameter(&on_a(&s.clamp(N::zero(), N::one())), b0, b1);
+        let s = on_segment_parameter(&on_b(&t), a0, a1);
+        candidates.push((s, t));
+    }
+    candidates.extend([
+        (N::zero(), on_segment_parameter(a0, b0, b1)),
+        (N::one(), on_segment_parameter(a1, b0, b1)),
+        (on_segment_parameter(b0, a0, a1), N::zero()),
+        (on_segment_parameter(b1, a0, a1), N::one()),
+    ]);
+    let mut closest = None;
+    for (s, t) in candidates {
+        let d = (on_a(&s) - on_b(&t)).magnitude_squared();
+        match &closest {
+            Some((best, _, _)) if *best <= d => {}
+            _ => closest = Some((d, s, t)),
+        }
+    }
+    let (_, s, t) = closest.unwrap();
+    (s, t)
+}
+
+/// The parameter of the closest point to `p` on the segment `a b`, which is zero if the segment
+/// has no length.
+fn on_segment_parameter<N: RealField>(p: &Point3<N>, a: &Point3<N>, b: &Point3<N>) -> N {
+    let length_squared = (b - a).magnitude_squared();
+    if length_squared > N::zero() {
+        ((p - a).dot(&(b - a)) / length_squared).clamp(N::zero(), N::one())
+    } else {
+        N::zero()
+    }
+}
+
+/// When the lines are (nearly) parallel, returns None.  Otherwise, returns the point parameter
+/// for line a.
 pub fn line_line_parameter_3d<N: RealField>(
     a0: &Point3<N>,
     a1: &Point3<N>,
     b0: &Point3<N>,
     b1: &Point3<N>,
 ) -> Option<N> {
-    //  TODO: handle nearly parallel lines.
-    let [a0x, a0y, a0z] = [a0.x.clone(), a0.y.clone(), a0.z.clone()];
-    let [a1x, a1y, a1z] = [a1.x.clone(), a1.y.clone(), a1.z.clone()];
-    let [b0x, b0y, b0z] = [b0.x.clone(), b0.y.clone(), b0.z.clone()];
-    let [b1x, b1y, b1z] = [b1.x.clone(), b1.y.clone(), b1.z.clone()];
-    let two = N::one() + N::one();
-    let four = two.clone() + two.clone();
-    let x = b0x.clone();
-    let tmp2 = x.powi(2);
-    let x = b0y.clone();
-    let tmp9 = x.powi(2);
-    let x = a1z.clone();
-    let tmp4 = x.powi(2);
-    let x = a0x.clone();
-    let tmp8 = x.powi(2);
-    let x = b0z.clone();
-    let tmp18 = x.powi(2);
-    let x = a1x.clone();
-    let tmp12 = x.powi(2);
-    let x = a1y.clone();
-    let tmp1 = x.powi(2);
-    let x = b1x.clone();
-    let tmp29 = x.powi(2);
-    let x = b1y.clone();
-    let tmp41 = x.powi(2);
-    let tmp47 = -two.clone() * b0x.clone() * b1x.clone();
-    let tmp48 = -two.clone() * b0y.clone() * b1y.clone();
-    let tmp49 = tmp2.clone()
-        + tmp9.clone()
-        + tmp47.clone().clone()
-        + tmp29.clone()
-        + tmp48.clone()
-        + tmp41.clone();
-    let x = b1z.clone();
-    let tmp75 = x.powi(2);
-    let tmp54 = -b1x.clone();
-    let tmp55 = b0x.clone() + tmp54.clone();
-    let tmp56 = a0x.clone() * tmp55.clone();
-    let tmp60 = -b1z.clone();
-    let tmp61 = b0z.clone() + tmp60.clone();
-    let tmp81 = -two.clone() * b0z.clone() * b1z.clone();
-    let tmp82 = tmp2.clone()
-        + tmp18.clone()
-        + tmp47.clone()
-        + tmp29.clone()
-        + tmp81.clone()
-        + tmp75.clone();
-    let tmp10 = tmp8.clone() * tmp9.clone();
-    let tmp19 = tmp8.clone() * tmp18.clone();
-    let tmp34 = -two.clone() * tmp8.clone() * b0y.clone() * b1y.clone();
-    let tmp42 = tmp8.clone() * tmp41.clone();
-    let x = a0z.clone();
-    let tmp46 = x.powi(2);
-    let tmp50 = tmp46.clone() * tmp49.clone();
-    let tmp68 = -two.clone() * tmp8.clone() * b0z.clone() * b1z.clone();
-    let tmp76 = tmp8.clone() * tmp75.clone();
-    let tmp51 = a1z.clone() * tmp49.clone();
-    let x = a0y.clone();
-    let tmp80 = x.powi(2);
-    let tmp83 = tmp80.clone() * tmp82.clone();
-    let tmp84 = -b1y.clone();
-    let tmp85 = b0y.clone() + tmp84.clone();
-    let tmp94 = a1y.clone() * tmp82.clone();
-    let denom = tmp1.clone() * tmp2.clone()
-        + tmp4.clone() * tmp2.clone()
-        + two.clone() * a0x.clone() * a1y.clone() * b0x.clone() * b0y.clone()
-        - two.clone() * a1x.clone() * a1y.clone() * b0x.clone() * b0y.clone()
-        + tmp10.clone()
-        - two.clone() * a0x.clone() * a1x.clone() * tmp9.clone()
-        + tmp12.clone() * tmp9.clone()
-        + tmp4.clone() * tmp9.clone()
-        + two.clone() * a0x.clone() * a1z.clone() * b0x.clone() * b0z.clone()
-        - two.clone() * a1x.clone() * a1z.clone() * b0x.clone() * b0z.clone()
-        - two.clone() * a1y.clone() * a1z.clone() * b0y.clone() * b0z.clone()
-        + tmp19.clone()
-        - two.clone() * a0x.clone() * a1x.clone() * tmp18.clone()
-        + tmp12.clone() * tmp18.clone()
-        + tmp1.clone() * tmp18.clone()
-        - two.clone() * tmp1.clone() * b0x.clone() * b1x.clone()
-        - two.clone() * tmp4.clone() * b0x.clone() * b1x.clone()
-        - two.clone() * a0x.clone() * a1y.clone() * b0y.clone() * b1x.clone()
-        + two.clone() * a1x.clone() * a1y.clone() * b0y.clone() * b1x.clone()
-        - two.clone() * a0x.clone() * a1z.clone() * b0z.clone() * b1x.clone()
-        + two.clone() * a1x.clone() * a1z.clone() * b0z.clone() * b1x.clone()
-        + tmp1.clone() * tmp29.clone()
-        + tmp4.clone() * tmp29.clone()
-        - two.clone() * a0x.clone() * a1y.clone() * b0x.clone() * b1y.clone()
-        + two.clone() * a1x.clone() * a1y.clone() * b0x.clone() * b1y.clone()
-        + tmp34.clone()
-        + four.clone() * a0x.clone() * a1x.clone() * b0y.clone() * b1y.clone()
-        - two.clone() * tmp12.clone() * b0y.clone() * b1y.clone()
-        - two.clone() * tmp4.clone() * b0y.clone() * b1y.clone()
-        + two.clone() * a1y.clone() * a1z.clone() * b0z.clone() * b1y.clone()
-        + two.clone() * a0x.clone() * a1y.clone() * b1x.clone() * b1y.clone()
-        - two.clone() * a1x.clone() * a1y.clone() * b1x.clone() * b1y.clone()
-        + tmp42.clone()
-        - two.clone() * a0x.clone() * a1x.clone() * tmp41.clone()
-        + tmp12.clone() * tmp41.clone()
-        + tmp4.clone() * tmp41.clone()
-        + tmp50.clone()
-        - two.clone()
-            * a0z.clone()
-            * (tmp51.clone()
-                + (-(a1x.clone() * b0x.clone()) - a1y.clone() * b0y.clone()
-                    + tmp56.clone()
-                    + a1x.clone() * b1x.clone()
-                    + a1y.clone() * b1y.clone())
-                    * tmp61.clone())
-        - two.clone() * a0x.clone() * a1z.clone() * b0x.clone() * b1z.clone()
-        + two.clone() * a1x.clone() * a1z.clone() * b0x.clone() * b1z.clone()
-        + two.clone() * a1y.clone() * a1z.clone() * b0y.clone() * b1z.clone()
-        + tmp68.clone()
-        + four.clone() * a0x.clone() * a1x.clone() * b0z.clone() * b1z.clone()
-        - two.clone() * tmp12.clone() * b0z.clone() * b1z.clone()
-        - two.clone() * tmp1.clone() * b0z.clone() * b1z.clone()
-        + two.clone() * a0x.clone() * a1z.clone() * b1x.clone() * b1z.clone()
-        - two.clone() * a1x.clone() * a1z.clone() * b1x.clone() * b1z.clone()
-        - two.clone() * a1y.clone() * a1z.clone() * b1y.clone() * b1z.clone()
-        + tmp76.clone()
-        - two.clone() * a0x.clone() * a1x.clone() * tmp75.clone()
-        + tmp12.clone() * tmp75.clone()
-        + tmp1.clone() * tmp75.clone()
-        + tmp83.clone()
-        - two.clone()
-            * a0y.clone()
-            * (tmp85.clone()
-                * (tmp56.clone()
-                    + a1x.clone() * (-b0x.clone() + b1x.clone())
-                    + (a0z.clone() - a1z.clone()) * tmp61.clone())
-                + tmp94.clone());
-    if denom.is_zero() {
+    let (da, db) = (a1 - a0, b1 - b0);
+    let normal = da.cross(&db);
+    let normal_squared = normal.magnitude_squared();
+    //  `|da × db|²` is only accurate to a few ulps of `|da|² |db|²`, so below that the lines are
+    //  considered parallel.
+    let threshold = N::default_epsilon()
+        * (N::one() + N::one()).powi(4)
+        * da.magnitude_squared()
+        * db.magnitude_squared();
+    if normal_squared <= threshold {
         None
     } else {
-        Some(
-            (a0x.clone() * a1y.clone() * b0x.clone() * b0y.clone() + tmp10.clone()
-                - a0x.clone() * a1x.clone() * tmp9.clone()
-                + a0x.clone() * a1z.clone() * b0x.clone() * b0z.clone()
-                + tmp19.clone()
-                - a0x.clone() * a1x.clone() * tmp18.clone()
-                - a0x.clone() * a1y.clone() * b0y.clone() * b1x.clone()
-                - a1y.clone() * b0x.clone() * b0y.clone() * b1x.clone()
-                - a0x.clone() * tmp9.clone() * b1x.clone()
-                + a1x.clone() * tmp9.clone() * b1x.clone()
-                - a0x.clone() * a1z.clone() * b0z.clone() * b1x.clone()
-                - a1z.clone() * b0x.clone() * b0z.clone() * b1x.clone()
-                - a0x.clone() * tmp18.clone() * b1x.clone()
-                + a1x.clone() * tmp18.clone() * b1x.clone()
-                + a1y.clone() * b0y.clone() * tmp29.clone()
-                + a1z.clone() * b0z.clone() * tmp29.clone()
-                - a0x.clone() * a1y.clone() * b0x.clone() * b1y.clone()
-                + a1y.clone() * tmp2.clone() * b1y.clone()
-                + tmp34.clone()
-                + two.clone() * a0x.clone() * a1x.clone() * b0y.clone() * b1y.clone()
-                + a0x.clone() * b0x.clone() * b0y.clone() * b1y.clone()
-                - a1x.clone() * b0x.clone() * b0y.clone() * b1y.clone()
-                - a1z.clone() * b0y.clone() * b0z.clone() * b1y.clone()
-                + a1y.clone() * tmp18.clone() * b1y.clone()
-                + a0x.clone() * a1y.clone() * b1x.clone() * b1y.clone()
-                - a1y.clone() * b0x.clone() * b1x.clone() * b1y.clone()
-                + a0x.clone() * b0y.clone() * b1x.clone() * b1y.clone()
-                - a1x.clone() * b0y.clone() * b1x.clone() * b1y.clone()
-                + tmp42.clone()
-                - a0x.clone() * a1x.clone() * tmp41.clone()
-                - a0x.clone() * b0x.clone() * tmp41.clone()
-                + a1x.clone() * b0x.clone() * tmp41.clone()
-                + a1z.clone() * b0z.clone() * tmp41.clone()
-                + tmp50.clone()
-                - a0x.clone() * a1z.clone() * b0x.clone() * b1z.clone()
-                + a1z.clone() * tmp2.clone() * b1z.clone()
-                + a1z.clone() * tmp9.clone() * b1z.clone()
-                + tmp68.clone()
-                + two.clone() * a0x.clone() * a1x.clone() * b0z.clone() * b1z.clone()
-                + a0x.clone() * b0x.clone() * b0z.clone() * b1z.clone()
-                - a1x.clone() * b0x.clone() * b0z.clone() * b1z.clone()
-                - a1y.clone() * b0y.clone() * b0z.clone() * b1z.clone()
-                + a0x.clone() * a1z.clone() * b1x.clone() * b1z.clone()
-                - a1z.clone() * b0x.clone() * b1x.clone() * b1z.clone()
-                + a0x.clone() * b0z.clone() * b1x.clone() * b1z.clone()
-                - a1x.clone() * b0z.clone() * b1x.clone() * b1z.clone()
-                - a1z.clone() * b0y.clone() * b1y.clone() * b1z.clone()
-                - a1y.clone() * b0z.clone() * b1y.clone() * b1z.clone()
-                + tmp76.clone()
-                - a0x.clone() * a1x.clone() * tmp75.clone()
-                - a0x.clone() * b0x.clone() * tmp75.clone()
-                + a1x.clone() * b0x.clone() * tmp75.clone()
-                + a1y.clone() * b0y.clone() * tmp75.clone()
-                - a0z.clone()
-                    * (-(a1x.clone() * b0x.clone() * b0z.clone())
-                        - a1y.clone() * b0y.clone() * b0z.clone()
-                        + a1x.clone() * b0z.clone() * b1x.clone()
-                        - b0x.clone() * b0z.clone() * b1x.clone()
-                        + b0z.clone() * tmp29.clone()
-                        + a1y.clone() * b0z.clone() * b1y.clone()
-                        - b0y.clone() * b0z.clone() * b1y.clone()
-                        + b0z.clone() * tmp41.clone()
-                        + tmp51.clone()
-                        + two.clone() * a0x.clone() * tmp55.clone() * tmp61.clone()
-                        + a1x.clone() * b0x.clone() * b1z.clone()
-                        + tmp2.clone() * b1z.clone()
-                        + a1y.clone() * b0y.clone() * b1z.clone()
-                        + tmp9.clone() * b1z.clone()
-                        - a1x.clone() * b1x.clone() * b1z.clone()
-                        - b0x.clone() * b1x.clone() * b1z.clone()
-                        - a1y.clone() * b1y.clone() * b1z.clone()
-                        - b0y.clone() * b1y.clone() * b1z.clone())
-                + tmp83.clone()
-                - a0y.clone()
-                    * (-(a1x.clone() * b0x.clone() * b0y.clone())
-                        + two.clone() * a0z.clone() * b0y.clone() * b0z.clone()
-                        - a1z.clone() * b0y.clone() * b0z.clone()
-                        + a1x.clone() * b0y.clone() * b1x.clone()
-                        - b0x.clone() * b0y.clone() * b1x.clone()
-                        + b0y.clone() * tmp29.clone()
-                        + two.clone() * a0x.clone() * tmp55.clone() * tmp85.clone()
-                        + a1x.clone() * b0x.clone() * b1y.clone()
-                        + tmp2.clone() * b1y.clone()
-                        - two.clone() * a0z.clone() * b0z.clone() * b1y.clone()
-                        + a1z.clone() * b0z.clone() * b1y.clone()
-                        + tmp18.clone() * b1y.clone()
-                        - a1x.clone() * b1x.clone() * b1y.clone()
-                        - b0x.clone() * b1x.clone() * b1y.clone()
-                        - two.clone() * a0z.clone() * b0y.clone() * b1z.clone()
-                        + a1z.clone() * b0y.clone() * b1z.clone()
-                        - b0y.clone() * b0z.clone() * b1z.clone()
-                        + two.clone() * a0z.clone() * b1y.clone() * b1z.clone()
-                        - a1z.clone() * b1y.clone() * b1z.clone()
-                        - b0z.clone() * b1y.clone() * b1z.clone()
-                        + b0y.clone() * tmp75.clone()
-                        + tmp94.clone()))
-                / denom,
-        )
+        Some((b0 - a0).cross(&db).dot(&normal) / normal_squared)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::collision_detection::closest_point::{on_triangle_3d, point_line_parameter};
+    use crate::collision_detection::closest_point::{
+        on_triangle_3d, on_triangle_3d_barycentric, point_line_parameter,
+        segment_segment_parameters_3d,
+    };
     use crate::collision_detection::smallest_offset;
     use nalgebra::{vector, Point3, Vector3};
     use rand::distributions::Uniform;
@@ -515,4 +445,146 @@ mod tests {
             }
         }
     }
+    #[test]
+    fn check_tri_point_brute_force() {
+        let mut rng = StdRng::seed_from_u64(4321);
+        let dist = Uniform::new(-1., 1.);
+        let random_point =
+            |rng: &mut StdRng| Point3::new(rng.sample(dist), rng.sample(dist), rng.sample(dist));
+        for i in 0..200 {
+            let a = random_point(&mut rng);
+            let b = random_point(&mut rng);
+            let c = match i % 4 {
+                //  Collinear.
+                1 => a + (b - a).scale(rng.sample(dist) * 2.),
+                //  Sliver.
+                2 => a + (b - a).scale(0.5) + random_point(&mut rng).coords.scale(1e-9),
+                //  Coincident vertices.
+                3 => a,
+                _ => random_point(&mut rng),
+            };
+            for _ in 0..20 {
+                let p = Point3::from(random_point(&mut rng).coords.scale(2.));
+                let (on, [alpha, beta, gamma]) = on_triangle_3d_barycentric(&p, &a, &b, &c);
+                assert!(alpha >= 0. && beta >= 0. && gamma >= 0.);
+                assert!((alpha + beta + gamma - 1f64).abs() < 1e-12);
+                let combined = a.coords.scale(alpha) + b.coords.scale(beta) + c.coords.scale(gamma);
+                assert!((on.coords - combined).norm() < 1e-12);
+                let mut brute = f64::INFINITY;
+                let n = 64;
+                for j in 0..=n {
+                    for k in 0..=(n - j) {
+                        let (u, v) = (j as f64 / n as f64, k as f64 / n as f64);
+                        let sample =
+                            a.coords.scale(1. - u - v) + b.coords.scale(u) + c.coords.scale(v);
+                        brute = brute.min((p.coords - sample).norm());
+                    }
+                }
+                let distance = (p - on).norm();
+                assert!(
+                    distance <= brute + 1e-12,
+                    "({i}): distance = {distance}, brute force = {brute}"
+                );
+                assert!(
+                    brute - distance < 0.1,
+                    "({i}): distance = {distance}, brute force = {brute}"
+                );
+            }
+        }
+    }
+
+    /// The closest distance between the segments, by sampling.
+    fn brute_segment_segment(
+        a0: &Point3<f64>,
+        a1: &Point3<f64>,
+        b0: &Point3<f64>,
+        b1: &Point3<f64>,
+    ) -> f64 {
+        let n = 200;
+        let mut brute = f64::INFINITY;
+        for i in 0..=n {
+            let a = a0 + (a1 - a0).scale(i as f64 / n as f64);
+            for j in 0..=n {
+                let b = b0 + (b1 - b0).scale(j as f64 / n as f64);
+                brute = brute.min((a - b).norm());
+            }
+        }
+        brute
+    }
+
+    #[test]
+    fn check_segment_segment_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2345);
+        let dist = Uniform::new(-1., 1.);
+        let random_point =
+            |rng: &mut StdRng| Point3::new(rng.sample(dist), rng.sample(dist), rng.sample(dist));
+        for i in 0..400 {
+            let a0 = random_point(&mut rng);
+            let a1 = random_point(&mut rng);
+            let b0 = random_point(&mut rng);
+            let b1 = match i % 4 {
+                //  Nearly parallel, with a shrinking angle.
+                1 | 2 => {
+                    let angle = 10f64.powi(-(i % 17));
+                    b0 + (a1 - a0).scale(rng.sample(dist))
+                        + random_point(&mut rng).coords.scale(angle)
+                }
+                //  Degenerate.
+                3 => b0,
+                _ => random_point(&mut rng),
+            };
+            let (s, t) = segment_segment_parameters_3d(&a0, &a1, &b0, &b1);
+            assert!(
+                (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t),
+                "({i}): s = {s}, t = {t}"
+            );
+            let distance = ((a0 + (a1 - a0).scale(s)) - (b0 + (b1 - b0).scale(t))).norm();
+            let brute = brute_segment_segment(&a0, &a1, &b0, &b1);
+            assert!(
+                distance <= brute + 1e-9,
+                "({i}): distance = {distance}, brute force = {brute}"
+            );
+            assert!(
+                brute - distance < 0.02,
+                "({i}): distance = {distance}, brute force = {brute}"
+            );
+        }
+    }
+
+    #[test]
+    fn check_segment_segment_across_parallel() {
+        let (a0, a1) = (Point3::new(0., 0., 0.), Point3::new(1., 0., 0.));
+        let mut last = None;
+        for k in 0..=40 {
+            //  The angle between the segments sweeps from 1e-2 down to exactly zero.
+            let angle = if k == 40 {
+                0.
+            } else {
+                10f64.powf(-2. - k as f64 * 0.4)
+            };
+            let (b0, b1) = (
+                Point3::new(0.25, 0., 1.),
+                Point3::new(1.75, 1.5 * angle, 1.),
+            );
+            let (s, t) = segment_segment_parameters_3d(&a0, &a1, &b0, &b1);
+            let distance = ((a0 + (a1 - a0).scale(s)) - (b0 + (b1 - b0).scale(t))).norm();
+            assert!(
+                (distance - 1.).abs() < 1e-2,
+                "angle = {angle}: distance = {distance}"
+            );
+            if let Some(last) = last {
+                assert!(f64::abs(distance - last) < 1e-2);
+            }
+            last = Some(distance);
+        }
+        //  Exactly parallel and overlapping, and coincident.
+        let (s, t) = segment_segment_parameters_3d(&a0, &a1, &a0, &a1);
+        assert_eq!((a0 + (a1 - a0).scale(s)), (a0 + (a1 - a0).scale(t)));
+        let (b0, b1) = (Point3::new(0.5, 1., 0.), Point3::new(1.5, 1., 0.));
+        let (s, t) = segment_segment_parameters_3d(&a0, &a1, &b0, &b1);
+        let distance = ((a0 + (a1 - a0).scale(s)) - (b0 + (b1 - b0).scale(t))).norm();
+        assert!((distance - 1.).abs() < 1e-12);
+        //  Both degenerate.
+        assert_eq!(segment_segment_parameters_3d(&a0, &a0, &a1, &a1), (0., 0.));
+    }
 }
@@ -1,4 +1,5 @@
 use super::{closest_point, smallest_offset};
+use super::distance_type::{self, EdgeEdgeDistanceType, PointTriangleDistanceType};
 use crate::collision_detection::segment::Segment;
 use crate::collision_detection::vertex::Vertex;
 
@@ -7,9 +8,7 @@ pub fn point_point<N: RealField, const D: usize>(o1: &Vertex<N, D>, o2: &Vertex<
 }
 
 pub fn point_segment<N: RealField, const D: usize>(o1: &Vertex<N, D>, o2: &Segment<N, D>) -> N {
-    closest_point::on_segment(&o1.x, &o2.0.x, &o2.1.x)
-        .coords
-        .magnitude_squared()
+    nalgebra::distance_squared(&o1.x, &closest_point::on_segment(&o1.x, &o2.0.x, &o2.1.x))
 }
 
 pub fn segment_segment_3d<N: RealField>(
@@ -18,32 +17,10 @@ pub fn segment_segment_3d<N: RealField>(
     b0: &Point3<N>,
     b1: &Point3<N>,
 ) -> N {
-    let t1 = closest_point::line_line_parameter_3d(a0, a1, b0, b1);
-    let t2 = closest_point::line_line_parameter_3d(b0, b1, a0, a1);
-    match (t1, t2) {
-        (Some(t1), Some(t2)) => {
-            let (t1, t2) = (t1.clamp(N::zero(), N::one()), t2.clamp(N::zero(), N::one()));
-            let a = a0.coords.scale(N::one() - t1.clone()) + a1.coords.scale(t1);
-            let b = b0.coords.scale(N::one() - t2.clone()) + b1.coords.scale(t2);
-            (a - b).magnitude_squared()
-        }
-        (None, None) => {
-            let v = a1 - a0;
-            let (b0, b1) = if v.dot(&(b0 - b1)).is_sign_positive() {
-                (b0, b1)
-            } else {
-                (b1, b0)
-            };
-            if (b0 - a1).dot(&v).is_sign_positive() {
-                (b0 - a1).magnitude_squared()
-            } else if (a0 - b1).dot(&v).is_sign_positive() {
-                (a0 - b1).magnitude_squared()
-            } else {
-                smallest_offset::between_par_lines_3d(a0, a1, b0).magnitude_squared()
-            }
-        }
-        _ => unreachable!(),
-    }
+    let (s, t) = closest_point::segment_segment_parameters_3d(a0, a1, b0, b1);
+    let a = a0.coords.scale(N::one() - s.clone()) + a1.coords.scale(s);
+    let b = b0.coords.scale(N::one() - t.clone()) + b1.coords.scale(t);
+    (a - b).magnitude_squared()
 }
 
 pub fn line_line_3d<N: RealField>(
@@ -213,3 +190,234 @@ pub fn aabb_aabb<RF: RealField, const D: usize>(
         .sup(&OVector::<RF, Const<D>>::from_element(RF::zero()))
         .magnitude_squared()
 }
+
+/// The squared distance between a point and a triangle, and its gradient and Hessian with
+/// respect to `(p, a, b, c)`.  Within each [`PointTriangleDistanceType`] these are those of the
+/// distance to the corresponding point, line or plane, and so are smooth.
+pub fn point_triangle_3d_derivatives<N: RealField>(
+    p: &Point3<N>,
+    a: &Point3<N>,
+    b: &Point3<N>,
+    c: &Point3<N>,
+) -> (N, OVector<N, Const<12>>, OMatrix<N, Const<12>, Const<12>>) {
+    let (o, l) = (N::zero(), N::one());
+    let x = [p, a, b, c];
+    match distance_type::point_triangle(p, a, b, c) {
+        PointTriangleDistanceType::PA => hull_derivatives(x, [l.clone(), -l, o.clone(), o], &[]),
+        PointTriangleDistanceType::PB => hull_derivatives(x, [l.clone(), o.clone(), -l, o], &[]),
+        PointTriangleDistanceType::PC => hull_derivatives(x, [l.clone(), o.clone(), o, -l], &[]),
+        PointTriangleDistanceType::PAB => hull_derivatives(
+            x,
+            [l.clone(), -l.clone(), o.clone(), o.clone()],
+            &[[o.clone(), l.clone(), -l, o]],
+        ),
+        PointTriangleDistanceType::PBC => hull_derivatives(
+            x,
+            [l.clone(), o.clone(), -l.clone(), o.clone()],
+            &[[o.clone(), o, l.clone(), -l]],
+        ),
+        PointTriangleDistanceType::PCA => hull_derivatives(
+            x,
+            [l.clone(), o.clone(), o.clone(), -l.clone()],
+            &[[o.clone(), -l.clone(), o, l]],
+        ),
+        PointTriangleDistanceType::PT => point_plane_derivatives(p, a, b, c),
+    }
+}
+
+/// The squared distance between two segments, and its gradient and Hessian with respect to
+/// `(a0, a1, b0, b1)`.  Within each [`EdgeEdgeDistanceType`] these are those of the distance
+/// between the corresponding points and lines, and so are smooth.
+pub fn segment_segment_3d_derivatives<N: RealField>(
+    a0: &Point3<N>,
+    a1: &Point3<N>,
+    b0: &Point3<N>,
+    b1: &Point3<N>,
+) -> (N, OVector<N, Const<12>>, OMatrix<N, Const<12>, Const<12>>) {
+    use EdgeEdgeDistanceType::*;
+    let (o, l) = (N::zero(), N::one());
+    let ty = distance_type::edge_edge(a0, a1, b0, b1);
+    //  The weights of the a side, then the b side, and the directions they can move in.
+    let (wa, da) = match ty {
+        A0B0 | A0B1 | A0B => ([l.clone(), o.clone()], None),
+        A1B0 | A1B1 | A1B => ([o.clone(), l.clone()], None),
+        AB0 | AB1 | AB => ([l.clone(), o.clone()], Some([-l.clone(), l.clone()])),
+    };
+    let (wb, db) = match ty {
+        A0B0 | A1B0 | AB0 => ([-l.clone(), o.clone()], None),
+        A0B1 | A1B1 | AB1 => ([o.clone(), -l.clone()], None),
+        A0B | A1B | AB => ([-l.clone(), o.clone()], Some([l.clone(), -l.clone()])),
+    };
+    let [wa0, wa1] = wa;
+    let [wb0, wb1] = wb;
+    let mut dw = Vec::with_capacity(2);
+    if let Some([d0, d1]) = da {
+        dw.push([d0, d1, o.clone(), o.clone()]);
+    }
+    if let Some([d0, d1]) = db {
+        dw.push([o.clone(), o, d0, d1]);
+    }
+    hull_derivatives([a0, a1, b0, b1], [wa0, wa1, wb0, wb1], &dw)
+}
+
+/// The squared distance between a point and a segment, and its gradient and Hessian with
+/// respect to `(p, a, b)`.
+pub fn point_segment_derivatives<N: RealField>(
+    p: &Point3<N>,
+    a: &Point3<N>,
+    b: &Point3<N>,
+) -> (N, OVector<N, Const<9>>, OMatrix<N, Const<9>, Const<9>>) {
+    let (o, l) = (N::zero(), N::one());
+    //  A segment of zero length is the point `a`.
+    let t = if a == b {
+        N::zero()
+    } else {
+        closest_point::point_line_parameter(p, a, b)
+    };
+    let (value, gradient, hessian) = if t <= N::zero() {
+        hull_derivatives([p, a, b, b], [l.clone(), -l, o.clone(), o], &[])
+    } else if t >= N::one() {
+        hull_derivatives([p, a, b, b], [l.clone(), o.clone(), -l, o], &[])
+    } else {
+        return point_line_derivatives(p, a, b);
+    };
+    (value, gradient.fixed_rows::<9>(0).into_owned(), hessian.fixed_view::<9, 9>(0, 0).into_owned())
+}
+
+/// The squared distance between two points, and its gradient and Hessian with respect to
+/// `(p0, p1)`.
+pub fn point_point_derivatives<N: RealField>(
+    p0: &Point3<N>,
+    p1: &Point3<N>,
+) -> (N, OVector<N, Const<6>>, OMatrix<N, Const<6>, Const<6>>) {
+    let (o, l) = (N::zero(), N::one());
+    let (value, gradient, hessian) =
+        hull_derivatives([p0, p1, p1, p1], [l.clone(), -l, o.clone(), o], &[]);
+    (value, gradient.fixed_rows::<6>(0).into_owned(), hessian.fixed_view::<6, 6>(0, 0).into_owned())
+}
+
+/// The squared distance between the point `p` and the line through `a` and `b`, and its
+/// gradient and Hessian with respect to `(p, a, b)`.
+pub fn point_line_derivatives<N: RealField>(
+    p: &Point3<N>,
+    a: &Point3<N>,
+    b: &Point3<N>,
+) -> (N, OVector<N, Const<9>>, OMatrix<N, Const<9>, Const<9>>) {
+    let (o, l) = (N::zero(), N::one());
+    let (value, gradient, hessian) = hull_derivatives(
+        [p, a, b, b],
+        [l.clone(), -l.clone(), o.clone(), o.clone()],
+        &[[o.clone(), l.clone(), -l, o]],
+    );
+    (value, gradient.fixed_rows::<9>(0).into_owned(), hessian.fixed_view::<9, 9>(0, 0).into_owned())
+}
+
+/// The squared distance between the point `p` and the plane through `a`, `b` and `c`, and its
+/// gradient and Hessian with respect to `(p, a, b, c)`.
+pub fn point_plane_derivatives<N: RealField>(
+    p: &Point3<N>,
+    a: &Point3<N>,
+    b: &Point3<N>,
+    c: &Point3<N>,
+) -> (N, OVector<N, Const<12>>, OMatrix<N, Const<12>, Const<12>>) {
+    let (o, l) = (N::zero(), N::one());
+    hull_derivatives(
+        [p, a, b, c],
+        [l.clone(), -l.clone(), o.clone(), o.clone()],
+        &[
+            [o.clone(), l.clone(), -l.clone(), o.clone()],
+            [o.clone(), l, o, -N::one()],
+        ],
+    )
+}
+
+/// The squared distance between the lines through `a0, a1` and `b0, b1`, and its gradient and
+/// Hessian with respect to `(a0, a1, b0, b1)`.  When the lines are parallel, this is the distance
+/// from a point on one of them to the other instead.
+pub fn line_line_derivatives<N: RealField>(
+    a0: &Point3<N>,
+    a1: &Point3<N>,
+    b0: &Point3<N>,
+    b1: &Point3<N>,
+) -> (N, OVector<N, Const<12>>, OMatrix<N, Const<12>, Const<12>>) {
+    let (o, l) = (N::zero(), N::one());
+    hull_derivatives(
+        [a0, a1, b0, b1],
+        [l.clone(), o.clone(), -l.clone(), o.clone()],
+        &[
+            [-l.clone(), l.clone(), o.clone(), o.clone()],
+            [o.clone(), o, l, -N::one()],
+        ],
+    )
+}
+
+/// Computes `|r|²` where `r = Σ w_k x_k`, with weights that are affine in a set of free
+/// parameters (`w0` plus a combination of the rows of `dw`), minimized over those parameters.
+/// This is the squared distance between the affine hulls of two features.  Also returns the
+/// gradient and the Hessian with respect to the coordinates of `x`, which follow from the
+/// implicit function theorem: `H = f_xx - f_xθ f_θθ⁻¹ f_θx`.
+///
+/// When the directions are (nearly) linearly dependent, the hull is a lower-dimensional feature
+/// (such as the point that a zero length edge collapses to, or the line through a degenerate
+/// triangle), and the derivatives are those of the distance to that feature.
+fn hull_derivatives<N: RealField>(
+    x: [&Point3<N>; 4],
+    w0: [N; 4],
+    dw: &[[N; 4]],
+) -> (N, OVector<N, Const<12>>, OMatrix<N, Const<12>, Const<12>>) {
+    let combine = |w: &[N; 4]| {
+        x.iter()
+            .zip(w)
+            .fold(Vector3::zeros(), |acc, (x, w)| acc + x.coords.scale(w.clone()))
+    };
+    let two = N::one() + N::one();
+    let m = dw.len();
+    let u = Vec::from_iter(dw.iter().map(combine));
+    let utu = DMatrix::from_fn(m, m, |i, j| u[i].dot(&u[j]));
+    //  The same relative threshold as `closest_point::line_line_parameter_3d`, below which the
+    //  determinant is all rounding error.  Dropping the shortest direction leaves a hull that
+    //  spans the same (lower-dimensional) feature.
+    let threshold = N::default_epsilon() * two.clone().powi(4) * utu.diagonal().product();
+    if m > 0 && utu.determinant() <= threshold {
+        let shortest = (0..m).fold(0, |k, j| if utu[(j, j)] < utu[(k, k)] { j } else { k });
+        let dw = Vec::from_iter(
+            dw.iter().enumerate().filter(|(j, _)| *j != shortest).map(|(_, dw)| dw.clone()),
+        );
+        return hull_derivatives(x, w0, &dw);
+    }
+    let inv_utu = utu.try_inverse().unwrap();
+    let r0 = combine(&w0);
+    let theta = -&inv_utu * DVector::from_fn(m, |i, _| u[i].dot(&r0));
+    let mut w = w0;
+    for (j, dw) in dw.iter().enumerate() {
+        for k in 0..4 {
+            w[k] += theta[j].clone() * dw[k].clone();
+        }
+    }
+    let r = combine(&w);
+    let mut gradient = OVector::<N, Const<12>>::zeros();
+    let mut hessian = OMatrix::<N, Const<12>, Const<12>>::zeros();
+    for k in 0..4 {
+        gradient
+            .fixed_rows_mut::<3>(3 * k)
+            .copy_from(&r.scale(two.clone() * w[k].clone()));
+        for l in 0..4 {
+            hessian
+                .fixed_view_mut::<3, 3>(3 * k, 3 * l)
+                .fill_diagonal(two.clone() * w[k].clone() * w[l].clone());
+        }
+    }
+    if m > 0 {
+        let mut f_xtheta = OMatrix::<N, Const<12>, Dyn>::zeros(m);
+        for (j, dw) in dw.iter().enumerate() {
+            for k in 0..4 {
+                f_xtheta.fixed_view_mut::<3, 1>(3 * k, j).copy_from(
+                    &(r.scale(two.clone() * dw[k].clone()) + u[j].scale(two.clone() * w[k].clone())),
+                );
+            }
+        }
+        //  f_θθ = 2 Uᵀ U
+        hessian -= &f_xtheta * inv_utu.scale(two.clone().recip()) * f_xtheta.transpose();
+    }
+    (r.magnitude_squared(), gradient, hessian)
+}
@@ -0,0 +1,181 @@
+//! Classification of which features of a pair of primitives are closest, following incremental
+//! potential contact.  Within each type, the distance is that between the affine hulls of the
+//! features, which is smooth.
+
+use crate::collision_detection::closest_point;
+
+/// The distance types of a point `p` and a triangle `a, b, c`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointTriangleDistanceType {
+    /// Closest to vertex `a`.
+    PA,
+    /// Closest to vertex `b`.
+    PB,
+    /// Closest to vertex `c`.
+    PC,
+    /// Closest to the interior of edge `a b`.
+    PAB,
+    /// Closest to the interior of edge `b c`.
+    PBC,
+    /// Closest to the interior of edge `c a`.
+    PCA,
+    /// Closest to the interior of the triangle.
+    PT,
+}
+
+/// The distance types of two edges `a0 a1` and `b0 b1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EdgeEdgeDistanceType {
+    /// Closest between vertices `a0` and `b0`.
+    A0B0,
+    /// Closest between vertices `a0` and `b1`.
+    A0B1,
+    /// Closest between vertices `a1` and `b0`.
+    A1B0,
+    /// Closest between vertices `a1` and `b1`.
+    A1B1,
+    /// Closest between the interior of edge `a` and vertex `b0`.
+    AB0,
+    /// Closest between the interior of edge `a` and vertex `b1`.
+    AB1,
+    /// Closest between vertex `a0` and the interior of edge `b`.
+    A0B,
+    /// Closest between vertex `a1` and the interior of edge `b`.
+    A1B,
+    /// Closest between the interiors of both edges.
+    AB,
+}
+
+/// Classifies the closest features of the point `p` and the triangle `a, b, c`.  Degenerate
+/// triangles are never classified as [`PointTriangleDistanceType::PT`].
+pub fn point_triangle<N: RealField>(
+    p: &Point3<N>,
+    a: &Point3<N>,
+    b: &Point3<N>,
+    c: &Point3<N>,
+) -> PointTriangleDistanceType {
+    let (e0, e1, v) = (b - a, c - a, p - a);
+    let (d00, d01, d11) = (e0.dot(&e0), e0.dot(&e1), e1.dot(&e1));
+    let (d20, d21) = (v.dot(&e0), v.dot(&e1));
+    let denom = d00.clone() * d11.clone() - d01.clone() * d01.clone();
+    //  The same relative threshold as `closest_point::on_triangle_3d_barycentric`, as `denom`
+    //  (`|e0 × e1|²`) loses all relative precision well before reaching zero.
+    if denom > N::default_epsilon().sqrt() * d00.clone() * d11.clone() {
+        let beta = (d11 * d20.clone() - d01.clone() * d21.clone()) / denom.clone();
+        let gamma = (d00 * d21 - d01 * d20) / denom;
+        if beta >= N::zero() && gamma >= N::zero() && beta + gamma <= N::one() {
+            return PointTriangleDistanceType::PT;
+        }
+    }
+    let vertices = [
+        (a, PointTriangleDistanceType::PA),
+        (b, PointTriangleDistanceType::PB),
+        (c, PointTriangleDistanceType::PC),
+    ];
+    let edges = [
+        (a, b, PointTriangleDistanceType::PAB),
+        (b, c, PointTriangleDistanceType::PBC),
+        (c, a, PointTriangleDistanceType::PCA),
+    ];
+    let mut closest = ((p - a).magnitude_squared(), PointTriangleDistanceType::PA);
+    for (v, ty) in vertices {
+        let d2 = (p - v).magnitude_squared();
+        if d2 < closest.0 {
+            closest = (d2, ty);
+        }
+    }
+    for (e0, e1, ty) in edges {
+        let t = closest_point::point_line_parameter(p, e0, e1);
+        if N::zero() < t && t < N::one() {
+            let on = e0.coords.scale(N::one() - t.clone()) + e1.coords.scale(t);
+            let d2 = (&p.coords - on).magnitude_squared();
+            if d2 < closest.0 {
+                closest = (d2, ty);
+            }
+        }
+    }
+    closest.1
+}
+
+/// Classifies the closest features of the edges `a0 a1` and `b0 b1`.  Parallel edges are never
+/// classified as [`EdgeEdgeDistanceType::AB`].
+pub fn edge_edge<N: RealField>(
+    a0: &Point3<N>,
+    a1: &Point3<N>,
+    b0: &Point3<N>,
+    b1: &Point3<N>,
+) -> EdgeEdgeDistanceType {
+    use EdgeEdgeDistanceType::*;
+    let (s, t) = closest_point::segment_segment_parameters_3d(a0, a1, b0, b1);
+    let side = |p: N| {
+        if p <= N::zero() {
+            0
+        } else if p >= N::one() {
+            1
+        } else {
+            2
+        }
+    };
+    match (side(s), side(t)) {
+        (0, 0) => A0B0,
+        (0, 1) => A0B1,
+        (1, 0) => A1B0,
+        (1, 1) => A1B1,
+        (2, 0) => AB0,
+        (2, 1) => AB1,
+        (0, _) => A0B,
+        (1, _) => A1B,
+        _ => AB,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collision_detection::distance_type::{
+        edge_edge, point_triangle, EdgeEdgeDistanceType, PointTriangleDistanceType,
+    };
+    use nalgebra::point;
+
+    #[test]
+    fn check_point_triangle() {
+        let (a, b, c) = (point![0., 0., 0.], point![1., 0., 0.], point![0., 1., 0.]);
+        let cases = [
+            (point![-1., -1., 0.5], PointTriangleDistanceType::PA),
+            (point![2., -0.5, -0.5], PointTriangleDistanceType::PB),
+            (point![-0.1, 1.5, 0.], PointTriangleDistanceType::PC),
+            (point![0.5, -1., 1.], PointTriangleDistanceType::PAB),
+            (point![1., 1., -1.], PointTriangleDistanceType::PBC),
+            (point![-0.5, 0.5, 0.2], PointTriangleDistanceType::PCA),
+            (point![0.2, 0.2, -3.], PointTriangleDistanceType::PT),
+        ];
+        for (p, ty) in cases {
+            assert_eq!(point_triangle(&p, &a, &b, &c), ty, "p = {p:?}");
+        }
+        //  A sliver triangle.
+        let c = point![2., 0., 0.];
+        assert_eq!(point_triangle(&point![0.5, 0.1, 0.], &a, &b, &c), PointTriangleDistanceType::PAB);
+        //  A nearly degenerate sliver, with the point right above its interior.
+        let c = point![2., 1e-6, 0.];
+        let p = point![1.5, 0.6e-6, 1.];
+        assert_ne!(point_triangle(&p, &a, &b, &c), PointTriangleDistanceType::PT);
+    }
+
+    #[test]
+    fn check_edge_edge() {
+        let (a0, a1) = (point![0., 0., 0.], point![1., 0., 0.]);
+        let cases = [
+            (point![0.5, -1., 1.], point![0.5, 1., 1.], EdgeEdgeDistanceType::AB),
+            (point![-1., -1., 1.], point![-1., 1., 1.], EdgeEdgeDistanceType::A0B),
+            (point![2., -1., 1.], point![2., 1., 1.], EdgeEdgeDistanceType::A1B),
+            (point![0.5, 1., 1.], point![0.5, 2., 1.], EdgeEdgeDistanceType::AB0),
+            (point![0.5, 2., 1.], point![0.5, 1., 1.], EdgeEdgeDistanceType::AB1),
+            (point![-1., 1., 0.], point![-1., 2., 0.], EdgeEdgeDistanceType::A0B0),
+            (point![-1., 2., 0.], point![-1., 1., 0.], EdgeEdgeDistanceType::A0B1),
+            (point![2., 1., 0.], point![2., 2., 0.], EdgeEdgeDistanceType::A1B0),
+            (point![2., 2., 0.], point![2., 1., 0.], EdgeEdgeDistanceType::A1B1),
+        ];
+        for (b0, b1, ty) in cases {
+            assert_eq!(edge_edge(&a0, &a1, &b0, &b1), ty, "b0 = {b0:?}, b1 = {b1:?}");
+        }
+    }
+}
@@ -58,8 +58,15 @@ pub fn between_par_lines_3d<N: RealField>(
 #[cfg(test)]
 mod tests {
     use crate::collision_detection::closest_point::line_line_parameter_3d;
+    use crate::collision_detection::distance_squared::{
+        line_line_derivatives, point_plane_derivatives, point_point_derivatives, point_segment,
+        point_segment_derivatives, point_triangle_3d, point_triangle_3d_derivatives,
+        segment_segment_3d_derivatives,
+    };
+    use crate::collision_detection::segment::Segment;
     use crate::collision_detection::smallest_offset::{between_par_lines_3d, point_to_line};
-    use nalgebra::{point, vector, Point3, Vector3};
+    use crate::collision_detection::vertex::Vertex;
+    use nalgebra::{point, vector, Const, OMatrix, OVector, Point3, Vector3};
     use rand::rngs::StdRng;
     use rand::{Rng, SeedableRng};
 
@@ -152,4 +159,154 @@ mod tests {
             }
         }
     }
+
+    /// Checks the derivatives of `f` against central finite differences at `x`.
+    fn check_derivatives<const N: usize>(
+        x: OVector<f64, Const<N>>,
+        f: impl Fn(
+            &OVector<f64, Const<N>>,
+        ) -> (
+            f64,
+            OVector<f64, Const<N>>,
+            OMatrix<f64, Const<N>, Const<N>>,
+        ),
+    ) {
+        let (_, gradient, hessian) = f(&x);
+        let epsilon = 1e-6;
+        for i in 0..N {
+            let mut plus = x;
+            plus[i] += epsilon;
+            let mut minus = x;
+            minus[i] -= epsilon;
+            let (plus, minus) = (f(&plus), f(&minus));
+            let difference = (plus.0 - minus.0) / (2. * epsilon);
+            assert!(
+                (difference - gradient[i]).abs() < 1e-6 * gradient[i].abs().max(1.),
+                "gradient {i}: {difference} != {}\nx = {x:?}",
+                gradient[i]
+            );
+            let difference = (plus.1 - minus.1) / (2. * epsilon);
+            let diff = (difference - hessian.column(i)).abs().max();
+            assert!(
+                diff < 1e-4 * hessian.abs().max().max(1.),
+                "hessian {i}: {diff}\nx = {x:?}"
+            );
+        }
+    }
+
+    fn point(x: &OVector<f64, Const<12>>, i: usize) -> Point3<f64> {
+        Point3::new(x[3 * i], x[3 * i + 1], x[3 * i + 2])
+    }
+
+    #[test]
+    fn check_point_triangle_derivatives() {
+        let mut rng = StdRng::seed_from_u64(2468);
+        for _ in 0..1000 {
+            let x = OVector::<f64, Const<12>>::from_fn(|_, _| rng.gen_range(-1.0..=1.0));
+            let (value, ..) = point_triangle_3d_derivatives(
+                &point(&x, 0),
+                &point(&x, 1),
+                &point(&x, 2),
+                &point(&x, 3),
+            );
+            let expected =
+                point_triangle_3d(&point(&x, 0), &point(&x, 1), &point(&x, 2), &point(&x, 3));
+            assert!((value - expected).abs() < 1e-9, "{value} != {expected}");
+            check_derivatives(x, |x| {
+                point_triangle_3d_derivatives(
+                    &point(x, 0),
+                    &point(x, 1),
+                    &point(x, 2),
+                    &point(x, 3),
+                )
+            });
+        }
+    }
+
+    #[test]
+    fn check_segment_segment_derivatives() {
+        let mut rng = StdRng::seed_from_u64(1357);
+        for _ in 0..1000 {
+            let x = OVector::<f64, Const<12>>::from_fn(|_, _| rng.gen_range(-1.0..=1.0));
+            check_derivatives(x, |x| {
+                segment_segment_3d_derivatives(
+                    &point(x, 0),
+                    &point(x, 1),
+                    &point(x, 2),
+                    &point(x, 3),
+                )
+            });
+        }
+    }
+
+    #[test]
+    fn check_point_segment_derivatives() {
+        let mut rng = StdRng::seed_from_u64(9753);
+        for _ in 0..1000 {
+            let x = OVector::<f64, Const<9>>::from_fn(|_, _| rng.gen_range(-1.0..=1.0));
+            let p = |x: &OVector<f64, Const<9>>, i: usize| {
+                Point3::from(Vector3::new(x[3 * i], x[3 * i + 1], x[3 * i + 2]))
+            };
+            check_derivatives(x, |x| {
+                point_segment_derivatives(&p(x, 0), &p(x, 1), &p(x, 2))
+            });
+        }
+    }
+
+    #[test]
+    fn check_point_point_derivatives() {
+        let mut rng = StdRng::seed_from_u64(8642);
+        for _ in 0..100 {
+            let x = OVector::<f64, Const<6>>::from_fn(|_, _| rng.gen_range(-1.0..=1.0));
+            let p = |x: &OVector<f64, Const<6>>, i: usize| {
+                Point3::from(Vector3::new(x[3 * i], x[3 * i + 1], x[3 * i + 2]))
+            };
+            check_derivatives(x, |x| point_point_derivatives(&p(x, 0), &p(x, 1)));
+        }
+    }
+
+    #[test]
+    fn degenerate_features_fall_back() {
+        let p = point![1f64, 2., 3.];
+        let a = point![0., -1., 0.5];
+        //  A segment of zero length is a point.
+        let (value, gradient, hessian) = point_segment_derivatives(&p, &a, &a);
+        let (expected, expected_gradient, expected_hessian) = point_point_derivatives(&p, &a);
+        assert!((value - expected).abs() < 1e-12);
+        assert!((gradient.fixed_rows::<6>(0) - expected_gradient).norm() < 1e-12);
+        assert!((hessian.fixed_view::<6, 6>(0, 0) - expected_hessian).norm() < 1e-12);
+        assert!(gradient.fixed_rows::<3>(6).norm() == 0.);
+        //  A triangle with collinear vertices spans only a line.
+        let (value, gradient, hessian) = point_plane_derivatives(
+            &point![0f64, 0., 1.],
+            &point![-1., 0., 0.],
+            &point![0., 0., 0.],
+            &point![2., 0., 0.],
+        );
+        assert!((value - 1.).abs() < 1e-12);
+        assert!(gradient.iter().chain(hessian.iter()).all(|x| x.is_finite()));
+        //  Parallel lines are a constant distance apart.
+        let (value, gradient, hessian) = line_line_derivatives(
+            &point![0f64, 0., 0.],
+            &point![1., 0., 0.],
+            &point![5., 3., 4.],
+            &point![7., 3., 4.],
+        );
+        assert!((value - 25.).abs() < 1e-12);
+        assert!(gradient.iter().chain(hessian.iter()).all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn check_point_segment() {
+        let vertex = |x: Point3<f64>| Vertex {
+            x,
+            v: Vector3::zeros(),
+        };
+        let segment = Segment(vertex(point![10., 20., 30.]), vertex(point![12., 20., 30.]));
+        //  On the segment, far from the origin.
+        assert_eq!(point_segment(&vertex(point![11., 20., 30.]), &segment), 0.);
+        //  Beside the segment, and beyond its end.
+        assert_eq!(point_segment(&vertex(point![11., 23., 34.]), &segment), 25.);
+        assert_eq!(point_segment(&vertex(point![15., 24., 30.]), &segment), 25.);
+    }
 }
@@ -14,6 +14,125 @@ impl<RF: RealField> AffineTransform<RF> {
             transform: self.transform.scale(scale),
         }
     }
+
+    /// The transformation as a 3×4 matrix, with the translation as the first column followed by
+    /// the columns of the linear transform.
+    pub fn to_matrix(&self) -> Matrix3x4<RF> {
+        let mut matrix = Matrix3x4::zeros();
+        matrix.set_column(0, &self.translation);
+        matrix.fixed_view_mut::<3, 3>(0, 1).copy_from(&self.transform);
+        matrix
+    }
+
+    /// The inverse of [`Self::to_matrix`].
+    pub fn from_matrix(matrix: &Matrix3x4<RF>) -> Self {
+        Self {
+            translation: matrix.column(0).into_owned(),
+            transform: matrix.fixed_view::<3, 3>(0, 1).into_owned(),
+        }
+    }
+
+    /// The transformation that applies `other` first and then `self`.
+    pub fn compose(&self, other: &Self) -> Self {
+        Self {
+            translation: &self.transform * &other.translation + &self.translation,
+            transform: &self.transform * &other.transform,
+        }
+    }
+
+    /// The inverse transformation, or `None` if the linear transform is singular.
+    pub fn try_inverse(&self) -> Option<Self> {
+        let transform = self.transform.clone().try_inverse()?;
+        Some(Self {
+            translation: -(&transform * &self.translation),
+            transform,
+        })
+    }
+
+    /// The world position `p + A x` of the body space point `x`.
+    pub fn transform_point(&self, x: &Point3<RF>) -> Point3<RF> {
+        Point3::from(&self.transform * &x.coords + &self.translation)
+    }
+
+    /// The world direction `A v` of the body space vector `v`, which ignores the translation.
+    pub fn transform_vector(&self, v: &Vector3<RF>) -> Vector3<RF> {
+        &self.transform * v
+    }
+
+    /// The world positions of the body space points `x`, such as the vertices of a mesh.
+    pub fn transform_points(&self, x: &[Point3<RF>]) -> Vec<Point3<RF>> {
+        Vec::from_iter(x.iter().map(|x| self.transform_point(x)))
+    }
+
+    /// The tightest world space box around the body space box from `min` to `max`, as its
+    /// minimum and maximum corners.
+    pub fn transform_aabb(&self, min: &Point3<RF>, max: &Point3<RF>) -> (Point3<RF>, Point3<RF>) {
+        let half = (RF::one() + RF::one()).recip();
+        let center = self.transform_point(&Point3::from(
+            (&min.coords + &max.coords).scale(half.clone()),
+        ));
+        let extent = self.transform.abs() * (&max.coords - &min.coords).scale(half);
+        (center.clone() - &extent, center + extent)
+    }
+
+    /// The 3×12 Jacobian `J(x)` of [`Self::transform_point`] with respect to the 12-vector of
+    /// the transformation, so that `p + A x = J(x) q`.  It only depends on the body space point.
+    pub fn jacobian(x: &Point3<RF>) -> OMatrix<RF, Const<3>, Const<12>> {
+        let mut jacobian = OMatrix::<RF, Const<3>, Const<12>>::zeros();
+        for i in 0..3 {
+            jacobian[(i, i)] = RF::one();
+            for j in 0..3 {
+                jacobian[(i, 3 + 3 * i + j)] = x[j].clone();
+            }
+        }
+        jacobian
+    }
+
+    /// The polar decomposition `A = R S` of the linear transform.  It goes through the singular
+    /// value decomposition, so it holds up for near singular transforms.  A transform that
+    /// mirrors (with a negative determinant) still gets a proper rotation, with the reflection
+    /// left in the stretch along its smallest principal axis.
+    pub fn polar_decomposition(&self) -> PolarDecomposition<RF> {
+        let svd = self.transform.clone().svd(true, true);
+        let (mut u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+        let mut singular_values = svd.singular_values;
+        if (&u * &v_t).determinant() < RF::zero() {
+            let smallest = singular_values.imin();
+            singular_values[smallest] = -singular_values[smallest].clone();
+            u.column_mut(smallest).neg_mut();
+        }
+        PolarDecomposition {
+            rotation: Rotation3::from_matrix_unchecked(&u * &v_t),
+            stretch: v_t.transpose() * Matrix3::from_diagonal(&singular_values) * v_t,
+        }
+    }
+
+    /// The rigid motion closest to the transformation, made of the translation and the rotation
+    /// of [`Self::polar_decomposition`], for renderers and other engines.
+    pub fn to_isometry(&self) -> Isometry3<RF> {
+        Isometry3::from_parts(
+            Translation3::from(self.translation.clone()),
+            UnitQuaternion::from_rotation_matrix(&self.polar_decomposition().rotation),
+        )
+    }
+
+    /// How far the transformation has drifted from being rigid, as the squared Frobenius norm
+    /// `||A Aᵀ - I||²` that the orthogonality potential of the
+    /// [`InteractingObjects`](crate::kinematics::InteractingObjects) penalizes.  It should stay
+    /// small, and grows when the orthogonal stiffness is too low for the forces on the body.
+    pub fn drift(&self) -> RF {
+        (&self.transform * self.transform.transpose() - Matrix3::identity()).norm_squared()
+    }
+}
+
+/// The polar decomposition `A = R S` of the linear transform of an [`AffineTransform`], as
+/// returned by [`AffineTransform::polar_decomposition`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolarDecomposition<RF: RealField> {
+    /// The rotation `R` closest to `A`.
+    pub rotation: Rotation3<RF>,
+    /// The symmetric stretch and shear `S`, in the body space.
+    pub stretch: Matrix3<RF>,
 }
 
 impl<T: RealField, S: Storage<T, Const<12>>> From<Vector<T, Const<12>, S>> for AffineTransform<T> {
@@ -89,7 +208,7 @@ impl<RF: ClosedSub + nalgebra::Scalar> SubAssign for AffineTransform<RF> {
 #[cfg(test)]
 mod tests {
     use crate::configuration::AffineTransform;
-    use nalgebra::{Const, Matrix3, OVector, Vector3};
+    use nalgebra::{Const, Matrix3, OVector, Point3, Rotation3, Unit, Vector3};
 
     #[test]
     fn config_conversion() {
@@ -103,4 +222,116 @@ mod tests {
         assert_eq!(AffineTransform::from(vec), config);
         assert_eq!(OVector::<f32, Const<12>>::from(config), vec);
     }
+
+    fn decompose(transform: Matrix3<f64>) {
+        let config = AffineTransform {
+            translation: Vector3::new(1., 2., 3.),
+            transform,
+        };
+        let polar = config.polar_decomposition();
+        let rotation = polar.rotation.matrix();
+        assert!((rotation.transpose() * rotation - Matrix3::identity()).norm() < 1e-12);
+        assert!((rotation.determinant() - 1.).abs() < 1e-12);
+        assert!((polar.stretch - polar.stretch.transpose()).norm() < 1e-12);
+        assert!((rotation * polar.stretch - transform).norm() < 1e-12);
+        let isometry = config.to_isometry();
+        let x = Point3::new(0.3, -0.2, 0.5);
+        assert!(
+            ((isometry * x).coords - Vector3::new(1., 2., 3.) - rotation * x.coords).norm() < 1e-12
+        );
+    }
+
+    #[test]
+    fn polar_decomposition() {
+        let rotation =
+            Rotation3::from_axis_angle(&Unit::new_normalize(Vector3::new(1., -2., 1.)), 1.2);
+        let stretch = Matrix3::new(1.1, 0.05, 0., 0.05, 0.9, 0.02, 0., 0.02, 1.);
+        decompose(rotation.matrix() * stretch);
+        let polar = AffineTransform {
+            translation: Vector3::zeros(),
+            transform: rotation.matrix() * stretch,
+        }
+        .polar_decomposition();
+        assert!((polar.rotation.matrix() - rotation.matrix()).norm() < 1e-12);
+        assert!((polar.stretch - stretch).norm() < 1e-12);
+        //  Mirrored, flattened and collapsed.
+        decompose(rotation.matrix() * Matrix3::from_diagonal(&Vector3::new(1., 1., -0.8)));
+        decompose(rotation.matrix() * Matrix3::from_diagonal(&Vector3::new(1., 1e-12, 0.5)));
+        decompose(Matrix3::new(1., 2., 3., 2., 4., 6., -1., -2., -3.));
+        decompose(Matrix3::zeros());
+    }
+
+    #[test]
+    fn rigid_transforms_do_not_drift() {
+        let rotation =
+            Rotation3::from_axis_angle(&Unit::new_normalize(Vector3::new(3., 1., 2.)), 0.4);
+        let rigid = AffineTransform {
+            translation: Vector3::new(5., 0., -1.),
+            transform: rotation.into_inner(),
+        };
+        assert!(rigid.drift() < 1e-12);
+        let stretched = AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::from_diagonal(&Vector3::new(2., 1., 1.)),
+        };
+        assert_eq!(stretched.drift(), 9.);
+    }
+
+    #[test]
+    fn composition_and_inverse() {
+        let a = AffineTransform {
+            translation: Vector3::new(1., -2., 0.5),
+            transform: Matrix3::new(1.2, 0.1, -0.3, 0., 0.9, 0.2, 0.4, -0.1, 1.1),
+        };
+        let b = AffineTransform {
+            translation: Vector3::new(0., 3., -1.),
+            transform: Matrix3::new(0., -1., 0., 1., 0., 0., 0., 0., 2.),
+        };
+        let x = Point3::new(0.3, 0.7, -0.2);
+        let composed = a.compose(&b).transform_point(&x);
+        assert!((composed - a.transform_point(&b.transform_point(&x))).norm() < 1e-12);
+        let inverse = a.try_inverse().unwrap();
+        assert!((inverse.transform_point(&a.transform_point(&x)) - x).norm() < 1e-12);
+        let identity = a.compose(&inverse);
+        assert!(identity.translation.norm() < 1e-12);
+        assert!((identity.transform - Matrix3::identity()).norm() < 1e-12);
+        let v = Vector3::new(1., 0., 2.);
+        assert_eq!(a.transform_vector(&v), a.transform * v);
+        let q = OVector::<f64, Const<12>>::from(a.clone());
+        assert!((AffineTransform::jacobian(&x) * q - a.transform_point(&x).coords).norm() < 1e-12);
+        let flat = AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::from_diagonal(&Vector3::new(1., 0., 1.)),
+        };
+        assert_eq!(flat.try_inverse(), None);
+    }
+
+    #[test]
+    fn aabb_is_tight() {
+        let config = AffineTransform {
+            translation: Vector3::new(1., 2., 3.),
+            transform: Rotation3::from_axis_angle(&Vector3::z_axis(), std::f64::consts::FRAC_PI_4)
+                .into_inner()
+                * 2.,
+        };
+        let (min, max) = (Point3::new(-1., -1., 0.), Point3::new(1., 1., 0.5));
+        let corners = Vec::from_iter((0..8).map(|i| {
+            Point3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        }));
+        let corners = config.transform_points(&corners);
+        let (world_min, world_max) = config.transform_aabb(&min, &max);
+        for k in 0..3 {
+            let lowest = corners.iter().map(|x| x[k]).fold(f64::INFINITY, f64::min);
+            let highest = corners
+                .iter()
+                .map(|x| x[k])
+                .fold(f64::NEG_INFINITY, f64::max);
+            assert!((world_min[k] - lowest).abs() < 1e-12);
+            assert!((world_max[k] - highest).abs() < 1e-12);
+        }
+    }
 }
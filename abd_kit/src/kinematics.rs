@@ -1,38 +1,931 @@
-use std::hash::Hash;
 use hashbrown::HashMap;
 use crate::{AffineTransform, Moments};
 use crate::spatial::SpatialDB;
+use faer::sparse::SparseColMat;
 
-struct ObjectRef<'s, 'v, 'm, 'f, RF: RealField> {
-    state: &'s AffineTransform<RF>,
-    velocity: &'v AffineTransform<RF>,
-    moments: &'m Moments<RF>,
-    forces: &'f AffineTransform<RF>,
+pub mod contact;
+pub mod forces;
+pub mod friction;
+pub mod joint;
+mod solver;
+mod stiffness;
+
+pub use solver::*;
+pub use stiffness::*;
+
+/// The state of a single affine body, as seen by the integrator.
+#[derive(Clone, Debug)]
+pub struct ObjectRef<'s, 'v, 'm, 'f, RF: RealField> {
+    /// The configuration at the start of the time step.
+    pub state: &'s AffineTransform<RF>,
+    /// The velocity at the start of the time step.
+    pub velocity: &'v AffineTransform<RF>,
+    /// The (unscaled) volume integrals of the body.
+    pub moments: &'m Moments<RF>,
+    /// The generalized external forces acting on the body.
+    pub forces: &'f AffineTransform<RF>,
+    /// The uniform density that scales the moments, or one if they are mass weighted (see
+    /// [`Moments::from_tetrahedra`]).
+    pub density: RF,
+}
+
+/// A body of an [`InteractingObjects`], classified by how it moves.  Bodies that are not
+/// dynamic have infinite mass: they push the dynamic bodies around through the pair potentials,
+/// but are never solved for.
+#[derive(Clone, Debug)]
+pub enum Body<'s, 'v, 'm, 'f, RF: RealField> {
+    /// A body that is moved by its inertia, its forces and the pair potentials.
+    Dynamic(ObjectRef<'s, 'v, 'm, 'f, RF>),
+    /// A body that never moves.
+    Static(&'s AffineTransform<RF>),
+    /// A body that follows a prescribed trajectory, from `state` at the start of the time step to
+    /// `target` at its end.  The [`NewtonSolver`] moves it along the straight line in between, as
+    /// far as the contacts allow, so it pushes the dynamic bodies out of its way rather than
+    /// passing through them.
+    Kinematic {
+        state: &'s AffineTransform<RF>,
+        target: AffineTransform<RF>,
+    },
 }
 
 /// A set of interacting objects whose next state will rely upon each other.
 pub struct InteractingObjects<'s, 'v, 'm, 'f, 'c, RF: RealField, C> {
-    objects: Vec<ObjectRef<'s, 'v, 'm, 'f, RF>>,
+    objects: Vec<Body<'s, 'v, 'm, 'f, RF>>,
     close: HashMap<(usize, usize), &'c C>,
+    /// The stiffness `kappa` of the orthogonality potential.
+    orthogonal_stiffness: RF,
+    /// The force fields acting on single objects, by index.
+    fields: Vec<(usize, &'c SharedForceField<'c, RF>)>,
+    indices: Vec<usize>,
+    id: usize,
+}
+
+/// A potential that couples a pair of objects, such as a contact barrier.  The close pairs of an
+/// [`InteractingObjects`] have this added (scaled by the squared time step) to the incremental
+/// potential.
+pub trait PairPotential<RF: RealField> {
+    /// The value of the potential when the objects are at `a` and `b`.
+    fn potential(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> RF;
+    /// The gradient with respect to the transforms of `a` and `b`.
+    fn gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>);
+    /// The Hessian with respect to the 12-vectors (see [`AffineTransform`]) of `a` followed by
+    /// `b`.
+    fn hessian(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> OMatrix<RF, Const<24>, Const<24>>;
+    /// The largest fraction of the step `(da, db)`, starting from `(a, b)`, that can be taken
+    /// without the potential becoming infinite.  Potentials that are finite everywhere (springs,
+    /// for example) need not override this.
+    fn max_step(
+        &self,
+        a: &AffineTransform<RF>,
+        da: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+        db: &AffineTransform<RF>,
+    ) -> RF {
+        let _ = (a, da, b, db);
+        RF::one()
+    }
+}
+
+/// A potential acting on a single object, for loads that depend on where the object is, such as
+/// a spring pulling it towards a target, or wind.  Constant loads are better given as
+/// [`ObjectRef::forces`] (see [`forces`]).  Like the [`PairPotential`]s, it is added to the
+/// incremental potential scaled by the squared time step.  Its Hessian is projected to be positive
+/// semi-definite for projected Newton.
+pub trait ForceField<RF: RealField> {
+    /// The potential energy of the object at `q`.
+    fn potential(&self, q: &AffineTransform<RF>) -> RF;
+    /// The gradient with respect to the transform of the object, which is minus the generalized
+    /// force.
+    fn gradient(&self, q: &AffineTransform<RF>) -> AffineTransform<RF>;
+    /// The Hessian with respect to the 12-vector (see [`AffineTransform`]) of the object.
+    fn hessian(&self, q: &AffineTransform<RF>) -> OMatrix<RF, Const<12>, Const<12>>;
+}
+
+/// A [`ForceField`] as held by [`InteractingObjects`].  It is always `Sync`, so that the isles
+/// can be solved in parallel (with the `rayon` feature) without changing what can be held.
+pub type SharedForceField<'a, RF> = dyn ForceField<RF> + Sync + 'a;
+
+/// The sum of two potentials, such as a contact barrier and its friction.
+impl<RF: RealField, P: PairPotential<RF>, Q: PairPotential<RF>> PairPotential<RF> for (P, Q) {
+    fn potential(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> RF {
+        self.0.potential(a, b) + self.1.potential(a, b)
+    }
+
+    fn gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>) {
+        let (p_a, p_b) = self.0.gradient(a, b);
+        let (q_a, q_b) = self.1.gradient(a, b);
+        (p_a + q_a, p_b + q_b)
+    }
+
+    fn hessian(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> OMatrix<RF, Const<24>, Const<24>> {
+        self.0.hessian(a, b) + self.1.hessian(a, b)
+    }
+
+    fn max_step(
+        &self,
+        a: &AffineTransform<RF>,
+        da: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+        db: &AffineTransform<RF>,
+    ) -> RF {
+        self.0
+            .max_step(a, da, b, db)
+            .min(self.1.max_step(a, da, b, db))
+    }
+}
+
+impl<'s, 'v, 'm, 'f, RF: RealField> ObjectRef<'s, 'v, 'm, 'f, RF> {
+    /// The state that the body would reach if it were unconstrained, `q + h q' + h² M⁻¹ f`.
+    pub fn predicted_state(&self, time_step: RF) -> AffineTransform<RF> {
+        let inv_m = self
+            .moments
+            .clone()
+            .compact_inv_m(self.density.clone())
+            .to_matrix();
+        let acceleration = AffineTransform::from_matrix(&(self.forces.to_matrix() * inv_m));
+        self.state.clone()
+            + self.velocity.scaled_by(time_step.clone())
+            + acceleration.scaled_by(time_step.clone() * time_step)
+    }
+
+    /// The inertial part of the incremental potential, `½ (q - q̃)ᵀ M (q - q̃)`.
+    fn inertia_potential(&self, param: &AffineTransform<RF>, time_step: RF) -> RF {
+        let half = (RF::one() + RF::one()).recip();
+        let diff = param.to_matrix() - self.predicted_state(time_step).to_matrix();
+        let m = self.moments.compact_m(self.density.clone());
+        half * (&diff * m).dot(&diff)
+    }
+
+    /// The gradient of [`Self::inertia_potential`], `M (q - q̃)`.
+    fn inertia_gradient(&self, param: &AffineTransform<RF>, time_step: RF) -> AffineTransform<RF> {
+        let diff = param.to_matrix() - self.predicted_state(time_step).to_matrix();
+        AffineTransform::from_matrix(&(diff * self.moments.compact_m(self.density.clone())))
+    }
+}
+
+impl<'s, 'v, 'm, 'f, RF: RealField> Body<'s, 'v, 'm, 'f, RF> {
+    /// The configuration at the start of the time step.
+    pub fn state(&self) -> &'s AffineTransform<RF> {
+        match self {
+            Body::Dynamic(object) => object.state,
+            Body::Static(state) | Body::Kinematic { state, .. } => state,
+        }
+    }
+
+    /// The configuration that a body that is not dynamic is meant to reach by the end of the
+    /// time step.
+    pub fn prescribed_state(&self) -> Option<AffineTransform<RF>> {
+        match self {
+            Body::Dynamic(_) => None,
+            Body::Static(state) => Some((*state).clone()),
+            Body::Kinematic { target, .. } => Some(target.clone()),
+        }
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        matches!(self, Body::Dynamic(_))
+    }
 }
 
-impl<'s, 'v, 'm, 'f, 'c, 'd, RF: RealField, C> InteractingObjects<'s, 'v, 'm, 'f, 'c, RF, C> {
-    pub fn create_isles<Ctx, DB: SpatialDB<3, Ctx>>(&self, db: DB, ctx: Ctx) -> Vec<Self> {
-        let mut uf = partitions::PartitionVec::with_capacity(db.len());
-        uf.extend(db.all_items());
-        for (a, b) in db.self_close_indices(ctx) {
-            uf.union(a, b);
+impl<'s, 'v, 'm, 'f, 'c, RF: RealField, C: PairPotential<RF>>
+    InteractingObjects<'s, 'v, 'm, 'f, 'c, RF, C>
+{
+    /// A set of dynamic objects.
+    pub fn new(
+        objects: Vec<ObjectRef<'s, 'v, 'm, 'f, RF>>,
+        close: HashMap<(usize, usize), &'c C>,
+        orthogonal_stiffness: RF,
+    ) -> Self {
+        Self::from_bodies(
+            Vec::from_iter(objects.into_iter().map(Body::Dynamic)),
+            close,
+            orthogonal_stiffness,
+        )
+    }
+    /// A set of bodies, some of which may be static or kinematic.
+    pub fn from_bodies(
+        objects: Vec<Body<'s, 'v, 'm, 'f, RF>>,
+        close: HashMap<(usize, usize), &'c C>,
+        orthogonal_stiffness: RF,
+    ) -> Self {
+        Self {
+            indices: Vec::from_iter(0..objects.len()),
+            objects,
+            close,
+            orthogonal_stiffness,
+            fields: Vec::new(),
+            id: 0,
+        }
+    }
+    /// Adds the force `field` acting on the object at `index`.  Fields on objects that are not
+    /// dynamic have no effect.
+    pub fn with_force_field(
+        mut self,
+        index: usize,
+        field: &'c SharedForceField<'c, RF>,
+    ) -> Self {
+        debug_assert!(index < self.objects.len());
+        self.fields.push((index, field));
+        self
+    }
+    /// Splits these objects into isles: sets of objects that can be advanced independently of
+    /// each other.  Two objects end up in the same isle if the items in `db` that belong to them
+    /// (as given by `object_of`) are close to each other, or if they are coupled by a pair
+    /// potential.  Each isle keeps the [`Self::indices`] of its objects, and its position in the
+    /// returned vector is its [`Self::id`].
+    ///
+    /// Only dynamic bodies join isles together.  A static or kinematic body is added to every
+    /// isle that it is close to, and to none if it is not close to any dynamic body.
+    pub fn create_isles<Ctx, DB: SpatialDB<3, Ctx>>(
+        &self,
+        db: &DB,
+        ctx: Ctx,
+        object_of: impl Fn(&DB::Item) -> usize,
+    ) -> Vec<Self> {
+        let items = Vec::from_iter(db.all_items().map(|item| object_of(&item)));
+        let links = Vec::from_iter(
+            db.self_close_indices(ctx)
+                .map(|(a, b)| (items[a], items[b]))
+                .chain(self.close.keys().copied()),
+        );
+        let mut uf = partitions::PartitionVec::with_capacity(self.objects.len());
+        uf.extend(0..self.objects.len());
+        for &(a, b) in &links {
+            if self.objects[a].is_dynamic() && self.objects[b].is_dynamic() {
+                uf.union(a, b);
+            }
+        }
+        let mut sets = Vec::from_iter(
+            uf.all_sets()
+                .map(|set| {
+                    let mut set = Vec::from_iter(set.map(|(i, _)| i));
+                    set.sort_unstable();
+                    set
+                })
+                .filter(|set| self.objects[set[0]].is_dynamic()),
+        );
+        sets.sort_unstable_by_key(|set| set[0]);
+        let mut isle_of = vec![None; self.objects.len()];
+        for (id, set) in sets.iter().enumerate() {
+            for &i in set {
+                isle_of[i] = Some(id);
+            }
+        }
+        for &(a, b) in &links {
+            match (isle_of[a], isle_of[b]) {
+                (Some(id), None) => sets[id].push(b),
+                (None, Some(id)) => sets[id].push(a),
+                _ => {}
+            }
+        }
+        //  Bucket the pairs and fields by isle up front, rather than going through all of them
+        //  for every isle.
+        let mut pairs = vec![Vec::new(); sets.len()];
+        for (&(a, b), &pair) in &self.close {
+            if let Some(id) = isle_of[a].or(isle_of[b]) {
+                pairs[id].push(((a, b), pair));
+            }
+        }
+        let mut fields = vec![Vec::new(); sets.len()];
+        for &(i, field) in &self.fields {
+            if let Some(id) = isle_of[i] {
+                fields[id].push((i, field));
+            }
         }
-        uf.all_sets()
-            .map(|set| {
-                InteractingObjects { objects: todo!(), close: todo!() }
+        sets.into_iter()
+            .zip(pairs.into_iter().zip(fields))
+            .enumerate()
+            .map(|(id, (mut set, (pairs, fields)))| {
+                set.sort_unstable();
+                set.dedup();
+                let local = HashMap::<usize, usize>::from_iter(
+                    set.iter().enumerate().map(|(l, &i)| (i, l)),
+                );
+                let close = HashMap::from_iter(
+                    pairs
+                        .into_iter()
+                        .map(|((a, b), pair)| ((local[&a], local[&b]), pair)),
+                );
+                let fields =
+                    Vec::from_iter(fields.into_iter().map(|(i, field)| (local[&i], field)));
+                InteractingObjects {
+                    objects: Vec::from_iter(set.iter().map(|&i| self.objects[i].clone())),
+                    close,
+                    orthogonal_stiffness: self.orthogonal_stiffness.clone(),
+                    fields,
+                    indices: Vec::from_iter(set.iter().map(|&i| self.indices[i])),
+                    id,
+                }
             })
             .collect()
     }
+    /// The indices of the objects of this set, in the set that it was created from with
+    /// [`Self::create_isles`] (or `0..len` if it was created with [`Self::new`]).
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+    /// The position of this isle in the vector returned by [`Self::create_isles`].
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// The number of objects in this set.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+    /// Whether there are no objects in this set.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+    /// The incremental potential of the affine body dynamics, evaluated at `param` (one
+    /// transform per object).  Consists of the inertia term and the orthogonality potential
+    /// `kappa * ||A Aᵀ - I||²` of each dynamic object, the [`ForceField`]s on the dynamic
+    /// objects, and the [`PairPotential`] of each close pair.
     pub fn potential(&self, param: &[AffineTransform<RF>], time_step: RF) -> RF {
-        todo!()
+        debug_assert_eq!(param.len(), self.objects.len());
+        let h2 = time_step.clone() * time_step.clone();
+        let objects = self
+            .objects
+            .iter()
+            .zip(param)
+            .filter_map(|(body, q)| match body {
+                Body::Dynamic(object) => Some((object, q)),
+                _ => None,
+            })
+            .map(|(object, q)| {
+                object.inertia_potential(q, time_step.clone())
+                    + h2.clone() * self.orthogonal_stiffness.clone() * q.drift()
+            })
+            .fold(RF::zero(), |acc, e| acc + e);
+        let fields = self
+            .fields
+            .iter()
+            .filter(|(i, _)| self.objects[*i].is_dynamic())
+            .map(|(i, field)| field.potential(&param[*i]))
+            .fold(RF::zero(), |acc, e| acc + e);
+        let pairs = self
+            .close
+            .iter()
+            .map(|(&(a, b), pair)| pair.potential(&param[a], &param[b]))
+            .fold(RF::zero(), |acc, e| acc + e);
+        objects + h2 * (fields + pairs)
+    }
+    /// The gradient of [`Self::potential`] with respect to the transform of each object.
+    pub fn gradient(
+        &self,
+        param: &[AffineTransform<RF>],
+        time_step: RF,
+    ) -> Vec<AffineTransform<RF>> {
+        let h2 = time_step.clone() * time_step.clone();
+        let mut gradient = self.object_gradient(param, time_step);
+        for (&(a, b), pair) in &self.close {
+            let (grad_a, grad_b) = pair.gradient(&param[a], &param[b]);
+            gradient[a] += grad_a.scaled_by(h2.clone());
+            gradient[b] += grad_b.scaled_by(h2.clone());
+        }
+        gradient
+    }
+    /// [`Self::gradient`] with the entries of the objects that are not dynamic set to zero.
+    /// Used along with [`Self::projected_hessian`] by projected Newton.
+    fn free_gradient(
+        &self,
+        param: &[AffineTransform<RF>],
+        time_step: RF,
+    ) -> Vec<AffineTransform<RF>> {
+        let mut gradient = self.gradient(param, time_step);
+        for (body, g) in self.objects.iter().zip(&mut gradient) {
+            if !body.is_dynamic() {
+                *g = AffineTransform::from_matrix(&Matrix3x4::zeros());
+            }
+        }
+        gradient
+    }
+    /// The part of [`Self::gradient`] that does not involve the close pairs: the inertia,
+    /// orthogonality and force field terms of each dynamic object.
+    fn object_gradient(
+        &self,
+        param: &[AffineTransform<RF>],
+        time_step: RF,
+    ) -> Vec<AffineTransform<RF>> {
+        debug_assert_eq!(param.len(), self.objects.len());
+        let h2 = time_step.clone() * time_step.clone();
+        let mut gradient =
+            Vec::from_iter(self.objects.iter().zip(param).map(|(body, q)| match body {
+                Body::Dynamic(object) => {
+                    let mut gradient = object.inertia_gradient(q, time_step.clone());
+                    gradient.transform += orthogonality_gradient(&q.transform)
+                        .scale(h2.clone() * self.orthogonal_stiffness.clone());
+                    gradient
+                }
+                _ => AffineTransform::from_matrix(&Matrix3x4::zeros()),
+            }));
+        for &(i, field) in &self.fields {
+            if self.objects[i].is_dynamic() {
+                gradient[i] += field.gradient(&param[i]).scaled_by(h2.clone());
+            }
+        }
+        gradient
+    }
+    /// The Hessian of [`Self::potential`].  It is made up of 12×12 blocks, one for each pair of
+    /// objects, with each block using the 12-vector layout of [`AffineTransform`].
+    pub fn hessian(
+        &self,
+        param: &[AffineTransform<RF>],
+        time_step: RF,
+    ) -> SparseColMat<usize, RF> {
+        self.assemble_hessian(param, time_step, false)
+    }
+    /// The same as [`Self::hessian`], but with the orthogonality and [`ForceField`] terms
+    /// projected to be positive semi-definite, as used by projected Newton.  The Hessians of the
+    /// [`PairPotential`]s are used as is.  The rows and columns of the objects that are not
+    /// dynamic are replaced by those of the identity, so that Newton's method leaves them in
+    /// place.
+    pub fn projected_hessian(
+        &self,
+        param: &[AffineTransform<RF>],
+        time_step: RF,
+    ) -> SparseColMat<usize, RF> {
+        self.assemble_hessian(param, time_step, true)
+    }
+    fn assemble_hessian(
+        &self,
+        param: &[AffineTransform<RF>],
+        time_step: RF,
+        project: bool,
+    ) -> SparseColMat<usize, RF> {
+        debug_assert_eq!(param.len(), self.objects.len());
+        let h2 = time_step.clone() * time_step.clone();
+        let mut triplets = Vec::with_capacity(144 * (self.objects.len() + 4 * self.close.len()));
+        let mut fields = vec![Vec::new(); self.objects.len()];
+        for &(i, field) in &self.fields {
+            fields[i].push(field);
+        }
+        for (n, (body, q)) in self.objects.iter().zip(param).enumerate() {
+            let object = match body {
+                Body::Dynamic(object) => object,
+                _ if project => {
+                    let block = OMatrix::<RF, Const<12>, Const<12>>::identity();
+                    push_block(&mut triplets, n, n, block.fixed_view::<12, 12>(0, 0));
+                    continue;
+                }
+                _ => continue,
+            };
+            let m = object.moments.compact_m(object.density.clone());
+            let mut block = OMatrix::<RF, Const<12>, Const<12>>::zeros();
+            for i in 0..3 {
+                for j in 0..4 {
+                    for k in 0..4 {
+                        block[(dof_index(i, j), dof_index(i, k))] = m[(j, k)].clone();
+                    }
+                }
+            }
+            let orthogonality = orthogonality_hessian(&q.transform)
+                .scale(h2.clone() * self.orthogonal_stiffness.clone());
+            let mut view = block.fixed_view_mut::<9, 9>(3, 3);
+            if project {
+                view += project_psd(orthogonality);
+            } else {
+                view += orthogonality;
+            }
+            for field in &fields[n] {
+                let hessian = field.hessian(q).scale(h2.clone());
+                if project {
+                    block += project_psd(hessian);
+                } else {
+                    block += hessian;
+                }
+            }
+            push_block(&mut triplets, n, n, block.fixed_view::<12, 12>(0, 0));
+        }
+        for (&(a, b), pair) in &self.close {
+            let block = pair.hessian(&param[a], &param[b]).scale(h2.clone());
+            let free_a = !project || self.objects[a].is_dynamic();
+            let free_b = !project || self.objects[b].is_dynamic();
+            if free_a {
+                push_block(&mut triplets, a, a, block.fixed_view::<12, 12>(0, 0));
+            }
+            if free_a && free_b {
+                push_block(&mut triplets, a, b, block.fixed_view::<12, 12>(0, 12));
+                push_block(&mut triplets, b, a, block.fixed_view::<12, 12>(12, 0));
+            }
+            if free_b {
+                push_block(&mut triplets, b, b, block.fixed_view::<12, 12>(12, 12));
+            }
+        }
+        let n = 12 * self.objects.len();
+        SparseColMat::try_new_from_triplets(n, n, &triplets).unwrap()
+    }
+}
+
+/// The index in the 12-vector layout of [`AffineTransform`] of row `i` and column `j` of
+/// [`AffineTransform::to_matrix`].
+fn dof_index(i: usize, j: usize) -> usize {
+    if j == 0 {
+        i
+    } else {
+        3 + 3 * i + j - 1
+    }
+}
+
+/// Clamps the negative eigenvalues of a symmetric matrix to zero.
+pub(crate) fn project_psd<RF: RealField, const N: usize>(
+    matrix: OMatrix<RF, Const<N>, Const<N>>,
+) -> OMatrix<RF, Const<N>, Const<N>> {
+    let eigen = DMatrix::from_column_slice(N, N, matrix.as_slice()).symmetric_eigen();
+    if eigen.eigenvalues.iter().all(|e| *e >= RF::zero()) {
+        return matrix;
+    }
+    let clamped = eigen.eigenvalues.map(|e| e.max(RF::zero()));
+    let projected =
+        &eigen.eigenvectors * DMatrix::from_diagonal(&clamped) * eigen.eigenvectors.transpose();
+    OMatrix::<RF, Const<N>, Const<N>>::from_column_slice(projected.as_slice())
+}
+
+/// Adds the non-zero entries of the 12×12 block at block row `a` and block column `b`.
+fn push_block<RF: RealField, S: Storage<RF, Const<12>, Const<12>>>(
+    triplets: &mut Vec<(usize, usize, RF)>,
+    a: usize,
+    b: usize,
+    block: Matrix<RF, Const<12>, Const<12>, S>,
+) {
+    for j in 0..12 {
+        for i in 0..12 {
+            if !block[(i, j)].is_zero() {
+                triplets.push((12 * a + i, 12 * b + j, block[(i, j)].clone()));
+            }
+        }
+    }
+}
+
+/// The gradient of the orthogonality potential `||A Aᵀ - I||²` (see [`AffineTransform::drift`]),
+/// `4 (A Aᵀ - I) A`.
+pub fn orthogonality_gradient<RF: RealField>(a: &Matrix3<RF>) -> Matrix3<RF> {
+    let four = RF::from_u32(4).unwrap();
+    ((a * a.transpose() - Matrix3::identity()) * a).scale(four)
+}
+
+/// The Hessian of the orthogonality potential, with `A` flattened row by row.
+pub fn orthogonality_hessian<RF: RealField>(a: &Matrix3<RF>) -> OMatrix<RF, Const<9>, Const<9>> {
+    let four = RF::from_u32(4).unwrap();
+    let g = a * a.transpose() - Matrix3::identity();
+    let ata = a.transpose() * a;
+    OMatrix::<RF, Const<9>, Const<9>>::from_fn(|r, s| {
+        let (c, d) = (r / 3, r % 3);
+        let (e, f) = (s / 3, s % 3);
+        let mut entry = a[(c, f)].clone() * a[(e, d)].clone();
+        if c == e {
+            entry += ata[(f, d)].clone();
+        }
+        if d == f {
+            entry += g[(c, e)].clone();
+        }
+        four.clone() * entry
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kinematics::{Body, InteractingObjects, ObjectRef, PairPotential};
+    use crate::spatial::{Object, SpatialDB};
+    use crate::{AffineTransform, Moments};
+    use hashbrown::HashMap;
+    use nalgebra::{Const, Matrix3, OMatrix, OVector, Point3, Vector3};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// A zero length spring between the origins of two objects.
+    pub(super) struct Spring(pub f64);
+
+    impl PairPotential<f64> for Spring {
+        fn potential(&self, a: &AffineTransform<f64>, b: &AffineTransform<f64>) -> f64 {
+            0.5 * self.0 * (a.translation - b.translation).norm_squared()
+        }
+
+        fn gradient(
+            &self,
+            a: &AffineTransform<f64>,
+            b: &AffineTransform<f64>,
+        ) -> (AffineTransform<f64>, AffineTransform<f64>) {
+            let force = (a.translation - b.translation).scale(self.0);
+            (
+                AffineTransform {
+                    translation: force,
+                    transform: Matrix3::zeros(),
+                },
+                AffineTransform {
+                    translation: -force,
+                    transform: Matrix3::zeros(),
+                },
+            )
+        }
+
+        fn hessian(
+            &self,
+            _: &AffineTransform<f64>,
+            _: &AffineTransform<f64>,
+        ) -> OMatrix<f64, Const<24>, Const<24>> {
+            let mut hessian = OMatrix::<f64, Const<24>, Const<24>>::zeros();
+            for i in 0..3 {
+                hessian[(i, i)] = self.0;
+                hessian[(i + 12, i + 12)] = self.0;
+                hessian[(i, i + 12)] = -self.0;
+                hessian[(i + 12, i)] = -self.0;
+            }
+            hessian
+        }
+    }
+
+    pub(super) fn random_transform(rng: &mut StdRng) -> AffineTransform<f64> {
+        AffineTransform::from(OVector::<f64, Const<12>>::from_fn(|i, _| {
+            rng.gen_range(-0.5..=0.5) + if [3, 7, 11].contains(&i) { 1. } else { 0. }
+        }))
+    }
+
+    /// The unit cube centered on the origin.
+    pub(super) fn unit_cube() -> Moments<f64> {
+        Moments {
+            v: 1.,
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            xx: 1. / 12.,
+            xy: 0.,
+            xz: 0.,
+            yy: 1. / 12.,
+            yz: 0.,
+            zz: 1. / 12.,
+        }
+    }
+
+    pub(super) fn identity() -> AffineTransform<f64> {
+        AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::identity(),
+        }
+    }
+
+    pub(super) fn zero() -> AffineTransform<f64> {
+        AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::zeros(),
+        }
+    }
+
+    /// An object with unit density that is at rest at `state` and not subject to any forces.
+    pub(super) fn at_rest<'a>(
+        state: &'a AffineTransform<f64>,
+        moments: &'a Moments<f64>,
+        zero: &'a AffineTransform<f64>,
+    ) -> ObjectRef<'a, 'a, 'a, 'a, f64> {
+        ObjectRef {
+            state,
+            velocity: zero,
+            moments,
+            forces: zero,
+            density: 1.,
+        }
+    }
+
+    #[test]
+    fn potential_minimized_at_prediction() {
+        let (state, moments) = (identity(), unit_cube());
+        let velocity = AffineTransform {
+            translation: Vector3::new(1., -2., 0.5),
+            transform: Matrix3::zeros(),
+        };
+        let forces = AffineTransform {
+            translation: Vector3::new(0., 0., -9.8),
+            transform: Matrix3::zeros(),
+        };
+        let object = ObjectRef {
+            state: &state,
+            velocity: &velocity,
+            moments: &moments,
+            forces: &forces,
+            density: 2.,
+        };
+        let predicted = object.predicted_state(0.1);
+        assert!((predicted.translation - Vector3::new(0.1, -0.2, 0.05 - 0.049)).norm() < 1e-12);
+        assert!((predicted.transform - Matrix3::identity()).norm() < 1e-12);
+        let objects: InteractingObjects<_, Spring> =
+            InteractingObjects::new(vec![object], HashMap::new(), 1e3);
+        let minimum = objects.potential(std::slice::from_ref(&predicted), 0.1);
+        assert!(minimum.abs() < 1e-12);
+        let offset = AffineTransform {
+            translation: Vector3::new(0.25, 0., 0.),
+            transform: Matrix3::zeros(),
+        };
+        //  A pure translation only sees the total mass.
+        let shifted = objects.potential(&[predicted + offset], 0.1);
+        assert!((shifted - 0.5 * 2. * 0.25f64.powi(2)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn orthogonality_penalizes_stretching() {
+        let (state, moments, zero) = (identity(), unit_cube(), zero());
+        let object = at_rest(&state, &moments, &zero);
+        let objects: InteractingObjects<_, Spring> =
+            InteractingObjects::new(vec![object], HashMap::new(), 10.);
+        let stretched = AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::from_diagonal(&Vector3::new(2., 1., 1.)),
+        };
+        let potential = objects.potential(std::slice::from_ref(&stretched), 0.5);
+        let inertia = 0.5 * (stretched.to_matrix() - state.to_matrix())
+            .dot(&((stretched.to_matrix() - state.to_matrix()) * moments.compact_m(1.)));
+        assert!((potential - inertia - 0.25 * 10. * 9.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let mut rng = StdRng::seed_from_u64(4321);
+        let moments = unit_cube();
+        let states = [random_transform(&mut rng), random_transform(&mut rng)];
+        let velocities = [random_transform(&mut rng), random_transform(&mut rng)];
+        let forces = [random_transform(&mut rng), random_transform(&mut rng)];
+        let objects = Vec::from_iter((0..2).map(|i| ObjectRef {
+            state: &states[i],
+            velocity: &velocities[i],
+            moments: &moments,
+            forces: &forces[i],
+            density: 1.5,
+        }));
+        let spring = Spring(20.);
+        let objects: InteractingObjects<_, Spring> =
+            InteractingObjects::new(objects, HashMap::from_iter([((0, 1), &spring)]), 5.);
+        let time_step = 0.1;
+        let param = [random_transform(&mut rng), random_transform(&mut rng)];
+        let flat = |param: &[AffineTransform<f64>]| {
+            Vec::from_iter(
+                param
+                    .iter()
+                    .flat_map(|q| OVector::<f64, Const<12>>::from(q.clone()).data.0[0]),
+            )
+        };
+        let unflat = |flat: &[f64]| {
+            Vec::from_iter(flat.chunks(12).map(|q| {
+                AffineTransform::from(OVector::<f64, Const<12>>::from_column_slice(q))
+            }))
+        };
+        let gradient = flat(&objects.gradient(&param, time_step));
+        let hessian = objects.hessian(&param, time_step).to_dense();
+        let epsilon = 1e-6;
+        for i in 0..24 {
+            let mut plus = flat(&param);
+            plus[i] += epsilon;
+            let mut minus = flat(&param);
+            minus[i] -= epsilon;
+            let (plus, minus) = (unflat(&plus), unflat(&minus));
+            let difference = (objects.potential(&plus, time_step)
+                - objects.potential(&minus, time_step))
+                / (2. * epsilon);
+            assert!(
+                (difference - gradient[i]).abs() < 1e-5,
+                "{i}: {difference} != {}",
+                gradient[i]
+            );
+            let (plus, minus) = (
+                flat(&objects.gradient(&plus, time_step)),
+                flat(&objects.gradient(&minus, time_step)),
+            );
+            for j in 0..24 {
+                let difference = (plus[j] - minus[j]) / (2. * epsilon);
+                assert!(
+                    (difference - hessian[(j, i)]).abs() < 1e-5,
+                    "({j}, {i}): {difference} != {}",
+                    hessian[(j, i)]
+                );
+            }
+        }
+    }
+
+    /// A part of an object, with a spatial database that has its close pairs predetermined.
+    #[derive(Clone, Debug, PartialEq)]
+    pub(super) struct Part(pub usize);
+
+    pub(super) struct Fixed(pub Vec<Part>, pub Vec<(usize, usize)>);
+
+    impl Object<3, ()> for Part {
+        type RF = f64;
+
+        fn aabb_min(&self, _: ()) -> Point3<f64> {
+            Point3::origin()
+        }
+
+        fn aabb_max(&self, _: ()) -> Point3<f64> {
+            Point3::origin()
+        }
+
+        fn aabb_min_index(&self, _: (), _: usize) -> f64 {
+            0.
+        }
+
+        fn aabb_max_index(&self, _: (), _: usize) -> f64 {
+            0.
+        }
+
+        fn interacts_with(&self, other: &Self, _: ()) -> bool {
+            self.0 != other.0
+        }
     }
-    pub fn gradient(&self, param: &[AffineTransform<RF>], time_step: RF) -> RF {
-        todo!()
+
+    impl SpatialDB<3, ()> for Fixed {
+        type Item = Part;
+
+        fn self_close_pairs<'a>(&'a self, _: ()) -> impl Iterator<Item = (Part, Part)> + 'a
+        where
+            (): 'a,
+        {
+            self.1.iter().map(|&(a, b)| (self.0[a].clone(), self.0[b].clone()))
+        }
+
+        fn all_items<'a>(&'a self) -> impl Iterator<Item = Part> + 'a {
+            self.0.iter().cloned()
+        }
+
+        fn self_close_indices<'a>(&'a self, _: ()) -> impl Iterator<Item = (usize, usize)> + 'a
+        where
+            (): 'a,
+        {
+            self.1.iter().copied()
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn isles_are_connected_components() {
+        let (state, moments, zero) = (identity(), unit_cube(), zero());
+        let objects = Vec::from_iter((0..6).map(|_| at_rest(&state, &moments, &zero)));
+        let spring = Spring(1.);
+        let objects: InteractingObjects<_, Spring> =
+            InteractingObjects::new(objects, HashMap::from_iter([((4, 2), &spring)]), 1.);
+        //  Two parts per object, except for the last.
+        let db = Fixed(
+            Vec::from_iter((0..11).map(|i| Part(i / 2))),
+            vec![(0, 3), (5, 9), (4, 8), (6, 10)],
+        );
+        let isles = objects.create_isles(&db, (), |part| part.0);
+        assert_eq!(isles.len(), 3);
+        assert_eq!(isles[0].indices(), &[0, 1]);
+        assert_eq!(isles[1].indices(), &[2, 4]);
+        assert_eq!(isles[2].indices(), &[3, 5]);
+        for (id, isle) in isles.iter().enumerate() {
+            assert_eq!(isle.id(), id);
+        }
+        assert!(isles[0].close.is_empty());
+        assert!(isles[1].close.contains_key(&(1, 0)));
+        assert!(isles[2].close.is_empty());
+    }
+
+    #[test]
+    fn static_bodies_do_not_join_isles() {
+        let (state, moments, zero) = (identity(), unit_cube(), zero());
+        let dynamic = at_rest(&state, &moments, &zero);
+        let bodies = vec![
+            Body::Dynamic(dynamic.clone()),
+            Body::Static(&state),
+            Body::Dynamic(dynamic.clone()),
+            Body::Kinematic {
+                state: &state,
+                target: state.clone(),
+            },
+            Body::Static(&state),
+            Body::Dynamic(dynamic),
+        ];
+        let spring = Spring(1.);
+        let objects: InteractingObjects<_, Spring> = InteractingObjects::from_bodies(
+            bodies,
+            HashMap::from_iter([((0, 1), &spring), ((2, 1), &spring), ((3, 4), &spring)]),
+            1.,
+        );
+        let db = Fixed(Vec::from_iter((0..6).map(Part)), vec![(3, 5), (4, 1)]);
+        let isles = objects.create_isles(&db, (), |part| part.0);
+        assert_eq!(isles.len(), 3);
+        assert_eq!(isles[0].indices(), &[0, 1]);
+        assert_eq!(isles[1].indices(), &[1, 2]);
+        assert_eq!(isles[2].indices(), &[3, 5]);
+        assert!(isles[0].close.contains_key(&(0, 1)));
+        assert!(isles[1].close.contains_key(&(1, 0)));
+        //  The pair between the kinematic and the static body is not solved anywhere.
+        assert!(isles[2].close.is_empty());
+        assert!(!isles[2].objects[0].is_dynamic());
     }
 }
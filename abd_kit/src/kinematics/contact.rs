@@ -0,0 +1,620 @@
+//! The incremental potential contact barrier between affine bodies.
+
+use crate::collision_detection::accd::{
+    accd, PointTriangle3D, SegmentSegment3D, DEFAULT_DELTA_SCALE, DEFAULT_SCALE,
+};
+use crate::collision_detection::{distance_squared, Segment, TriangleFace, Vertex};
+use crate::kinematics::{project_psd, PairPotential};
+use crate::AffineTransform;
+
+/// A pair of primitives, in the body space of their respective objects, that may come into
+/// contact.  The first primitive always belongs to object `a` of the pair, and the second to
+/// object `b`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContactPrimitives<RF: RealField> {
+    /// A vertex of `a` against a triangle of `b`.
+    VertexFace(Point3<RF>, [Point3<RF>; 3]),
+    /// A triangle of `a` against a vertex of `b`.
+    FaceVertex([Point3<RF>; 3], Point3<RF>),
+    /// An edge of `a` against an edge of `b`.
+    EdgeEdge([Point3<RF>; 2], [Point3<RF>; 2]),
+}
+
+/// The IPC log barrier between all the close primitives of a pair of objects.  Usually filled
+/// with the vertex-face and edge-edge pairs produced by the broadphase.
+#[derive(Clone, Debug)]
+pub struct Contact<RF: RealField> {
+    pub primitives: Vec<ContactPrimitives<RF>>,
+    /// The distance `d̂` below which the barrier is active.
+    pub barrier_thickness: RF,
+    /// The barrier stiffness `κ`.
+    pub stiffness: RF,
+}
+
+/// A gradient and Hessian with respect to the four vertices of a pair of primitives.
+type Derivatives<RF> = (OVector<RF, Const<12>>, OMatrix<RF, Const<12>, Const<12>>);
+
+/// The part of an object that a primitive vertex belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Owner {
+    A,
+    B,
+}
+
+/// The IPC log barrier, `-(d² - d̂²)² ln(d² / d̂²)`, which is zero beyond `d̂` and goes to
+/// infinity as the distance goes to zero.  Takes the squared distances.
+pub fn barrier<RF: RealField>(d2: RF, dhat2: RF) -> RF {
+    if d2 >= dhat2 {
+        RF::zero()
+    } else {
+        let diff = d2.clone() - dhat2.clone();
+        -diff.clone() * diff * (d2 / dhat2).ln()
+    }
+}
+
+/// The derivative of [`barrier`] with respect to `d2`.
+pub fn barrier_derivative<RF: RealField>(d2: RF, dhat2: RF) -> RF {
+    if d2 >= dhat2 {
+        RF::zero()
+    } else {
+        let two = RF::one() + RF::one();
+        let diff = d2.clone() - dhat2.clone();
+        -two * diff.clone() * (d2.clone() / dhat2).ln() - diff.clone() * diff / d2
+    }
+}
+
+/// The second derivative of [`barrier`] with respect to `d2`.
+pub fn barrier_second_derivative<RF: RealField>(d2: RF, dhat2: RF) -> RF {
+    if d2 >= dhat2 {
+        RF::zero()
+    } else {
+        let two = RF::one() + RF::one();
+        let four = two.clone() + two.clone();
+        let diff = d2.clone() - dhat2.clone();
+        let ratio = diff / d2.clone();
+        -two * (d2 / dhat2).ln() - four * ratio.clone() + ratio.clone() * ratio
+    }
+}
+
+/// The IPC edge-edge mollifier, `(2 - c / ε) c / ε` below `ε` and one above it, where `c` is
+/// the squared norm of the cross product of the edges.  Multiplying the barrier of two edges by
+/// it keeps the energy smooth as the edges become parallel, where the closest points jump.
+pub fn mollifier<RF: RealField>(c: RF, threshold: RF) -> RF {
+    if c >= threshold {
+        RF::one()
+    } else {
+        let ratio = c / threshold;
+        (RF::one() + RF::one() - ratio.clone()) * ratio
+    }
+}
+
+/// The derivative of [`mollifier`] with respect to `c`.
+pub fn mollifier_derivative<RF: RealField>(c: RF, threshold: RF) -> RF {
+    if c >= threshold {
+        RF::zero()
+    } else {
+        let two = RF::one() + RF::one();
+        two * (threshold.clone() - c) / (threshold.clone() * threshold)
+    }
+}
+
+/// The second derivative of [`mollifier`] with respect to `c`.
+pub fn mollifier_second_derivative<RF: RealField>(c: RF, threshold: RF) -> RF {
+    if c >= threshold {
+        RF::zero()
+    } else {
+        let two = RF::one() + RF::one();
+        -two / (threshold.clone() * threshold)
+    }
+}
+
+impl<RF: RealField> ContactPrimitives<RF> {
+    /// The body space vertices of the primitives, the first primitive's first.
+    pub(super) fn vertices(&self) -> [(Owner, &Point3<RF>); 4] {
+        match self {
+            ContactPrimitives::VertexFace(p, [t0, t1, t2]) => {
+                [(Owner::A, p), (Owner::B, t0), (Owner::B, t1), (Owner::B, t2)]
+            }
+            ContactPrimitives::FaceVertex([t0, t1, t2], p) => {
+                [(Owner::B, p), (Owner::A, t0), (Owner::A, t1), (Owner::A, t2)]
+            }
+            ContactPrimitives::EdgeEdge([a0, a1], [b0, b1]) => {
+                [(Owner::A, a0), (Owner::A, a1), (Owner::B, b0), (Owner::B, b1)]
+            }
+        }
+    }
+
+    pub(super) fn world_vertices(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> [Point3<RF>; 4] {
+        self.vertices().map(|(owner, x)| match owner {
+            Owner::A => a.transform_point(x),
+            Owner::B => b.transform_point(x),
+        })
+    }
+
+    fn is_edge_edge(&self) -> bool {
+        matches!(self, ContactPrimitives::EdgeEdge(..))
+    }
+
+    /// The mollifier threshold `ε` of an edge-edge pair, which is `10⁻³ |a₁ - a₀|² |b₁ - b₀|²`
+    /// using the body space (rest) edges.  Other pairs are not mollified.
+    pub fn mollifier_threshold(&self) -> Option<RF> {
+        match self {
+            ContactPrimitives::EdgeEdge([a0, a1], [b0, b1]) => Some(
+                RF::from_f64(1e-3).unwrap()
+                    * (a1 - a0).magnitude_squared()
+                    * (b1 - b0).magnitude_squared(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// The squared distance between the primitives when the objects are at `a` and `b`.
+    pub fn distance_squared(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> RF {
+        let x = self.world_vertices(a, b);
+        distance_derivatives(&x, self.is_edge_edge()).0
+    }
+}
+
+impl<RF: RealField> Contact<RF> {
+    pub fn new(barrier_thickness: RF, stiffness: RF) -> Self {
+        Self {
+            primitives: Vec::new(),
+            barrier_thickness,
+            stiffness,
+        }
+    }
+
+    /// The number of primitive pairs that are closer than the barrier thickness.
+    pub fn active(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> usize {
+        let dhat2 = self.barrier_thickness.clone() * self.barrier_thickness.clone();
+        self.primitives
+            .iter()
+            .filter(|p| p.distance_squared(a, b) < dhat2)
+            .count()
+    }
+
+    /// The (mollified) barrier of a single pair of primitives, with unit stiffness, at its four
+    /// world space vertices.
+    fn primitive_potential(&self, primitives: &ContactPrimitives<RF>, x: &[Point3<RF>; 4]) -> RF {
+        let dhat2 = self.barrier_thickness.clone() * self.barrier_thickness.clone();
+        let d2 = distance_derivatives(x, primitives.is_edge_edge()).0;
+        let energy = barrier(d2, dhat2);
+        match primitives.mollifier_threshold() {
+            Some(threshold) if energy != RF::zero() => {
+                energy * mollifier(edge_cross_derivatives(x).0, threshold)
+            }
+            _ => energy,
+        }
+    }
+
+    /// The gradient and Hessian of the (mollified) barrier of a single pair of primitives, with
+    /// respect to its four world space vertices, with unit stiffness.  The Hessian is not
+    /// projected.
+    fn primitive_derivatives(
+        &self,
+        primitives: &ContactPrimitives<RF>,
+        x: &[Point3<RF>; 4],
+    ) -> Option<Derivatives<RF>> {
+        let dhat2 = self.barrier_thickness.clone() * self.barrier_thickness.clone();
+        let (d2, grad, hess) = distance_derivatives(x, primitives.is_edge_edge());
+        if d2 >= dhat2 {
+            return None;
+        }
+        let b = barrier(d2.clone(), dhat2.clone());
+        let db = barrier_derivative(d2.clone(), dhat2.clone());
+        let ddb = barrier_second_derivative(d2, dhat2);
+        let mut gradient = grad.scale(db.clone());
+        let mut hessian = (&grad * grad.transpose()).scale(ddb) + hess.scale(db.clone());
+        if let Some(threshold) = primitives.mollifier_threshold() {
+            let (c, c_grad, c_hess) = edge_cross_derivatives(x);
+            if c < threshold {
+                let m = mollifier(c.clone(), threshold.clone());
+                let dm = mollifier_derivative(c.clone(), threshold.clone());
+                let ddm = mollifier_second_derivative(c, threshold);
+                let cross = &c_grad * gradient.transpose();
+                hessian = hessian.scale(m.clone())
+                    + (&c_grad * c_grad.transpose()).scale(ddm * b.clone())
+                    + c_hess.scale(dm.clone() * b.clone())
+                    + (&cross + cross.transpose()).scale(dm.clone());
+                gradient = gradient.scale(m) + c_grad.scale(dm * b);
+            }
+        }
+        Some((gradient, hessian))
+    }
+
+    /// The smallest distance between the primitives when the objects are at `a` and `b`, or
+    /// `None` if there are no primitives.
+    pub fn min_distance(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> Option<RF> {
+        self.primitives
+            .iter()
+            .map(|p| p.distance_squared(a, b))
+            .reduce(|acc, d2| acc.min(d2))
+            .map(|d2| d2.sqrt())
+    }
+
+    /// The gradient of the barrier with unit stiffness, that is [`PairPotential::gradient`]
+    /// divided by [`Self::stiffness`].
+    pub fn barrier_gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>) {
+        let mut gradient = OVector::<RF, Const<24>>::zeros();
+        for primitives in &self.primitives {
+            let x = primitives.world_vertices(a, b);
+            if let Some((grad, _)) = self.primitive_derivatives(primitives, &x) {
+                for (k, (owner, x)) in primitives.vertices().into_iter().enumerate() {
+                    let mut view = gradient.fixed_rows_mut::<12>(owner.offset());
+                    view += AffineTransform::jacobian(x).transpose() * grad.fixed_rows::<3>(3 * k);
+                }
+            }
+        }
+        (
+            AffineTransform::from(gradient.fixed_rows::<12>(0)),
+            AffineTransform::from(gradient.fixed_rows::<12>(12)),
+        )
+    }
+}
+
+impl<RF: RealField> PairPotential<RF> for Contact<RF> {
+    fn potential(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> RF {
+        self.primitives
+            .iter()
+            .map(|p| self.primitive_potential(p, &p.world_vertices(a, b)))
+            .fold(RF::zero(), |acc, e| acc + e)
+            * self.stiffness.clone()
+    }
+
+    fn gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>) {
+        let (grad_a, grad_b) = self.barrier_gradient(a, b);
+        (
+            grad_a.scaled_by(self.stiffness.clone()),
+            grad_b.scaled_by(self.stiffness.clone()),
+        )
+    }
+
+    fn hessian(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> OMatrix<RF, Const<24>, Const<24>> {
+        let mut hessian = OMatrix::<RF, Const<24>, Const<24>>::zeros();
+        for primitives in &self.primitives {
+            let x = primitives.world_vertices(a, b);
+            if let Some((_, hess)) = self.primitive_derivatives(primitives, &x) {
+                let hess = project_psd(hess.scale(self.stiffness.clone()));
+                let vertices = primitives.vertices();
+                for (k, (owner_k, x_k)) in vertices.iter().enumerate() {
+                    let j_k = AffineTransform::jacobian(x_k);
+                    for (l, (owner_l, x_l)) in vertices.iter().enumerate() {
+                        let mut view = hessian
+                            .fixed_view_mut::<12, 12>(owner_k.offset(), owner_l.offset());
+                        view += j_k.transpose()
+                            * hess.fixed_view::<3, 3>(3 * k, 3 * l)
+                            * AffineTransform::jacobian(x_l);
+                    }
+                }
+            }
+        }
+        hessian
+    }
+
+    fn max_step(
+        &self,
+        a: &AffineTransform<RF>,
+        da: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+        db: &AffineTransform<RF>,
+    ) -> RF {
+        let scale = RF::from_f64(DEFAULT_SCALE).unwrap();
+        let delta_scale = RF::from_f64(DEFAULT_DELTA_SCALE).unwrap();
+        let vertex = |x: &Point3<RF>, owner: Owner| match owner {
+            Owner::A => Vertex {
+                x: a.transform_point(x),
+                v: da.transform_point(x).coords,
+            },
+            Owner::B => Vertex {
+                x: b.transform_point(x),
+                v: db.transform_point(x).coords,
+            },
+        };
+        self.primitives
+            .iter()
+            .filter_map(|primitives| match primitives {
+                ContactPrimitives::VertexFace(p, [t0, t1, t2]) => {
+                    accd::<RF, 3, _, _, PointTriangle3D<RF>>(
+                        vertex(p, Owner::A),
+                        TriangleFace {
+                            a: vertex(t0, Owner::B),
+                            b: vertex(t1, Owner::B),
+                            c: vertex(t2, Owner::B),
+                        },
+                        RF::zero(),
+                        scale.clone(),
+                        self.barrier_thickness.clone(),
+                        RF::one(),
+                        delta_scale.clone(),
+                    )
+                }
+                ContactPrimitives::FaceVertex([t0, t1, t2], p) => {
+                    accd::<RF, 3, _, _, PointTriangle3D<RF>>(
+                        vertex(p, Owner::B),
+                        TriangleFace {
+                            a: vertex(t0, Owner::A),
+                            b: vertex(t1, Owner::A),
+                            c: vertex(t2, Owner::A),
+                        },
+                        RF::zero(),
+                        scale.clone(),
+                        self.barrier_thickness.clone(),
+                        RF::one(),
+                        delta_scale.clone(),
+                    )
+                }
+                ContactPrimitives::EdgeEdge([a0, a1], [b0, b1]) => {
+                    accd::<RF, 3, _, _, SegmentSegment3D<RF>>(
+                        Segment(vertex(a0, Owner::A), vertex(a1, Owner::A)),
+                        Segment(vertex(b0, Owner::B), vertex(b1, Owner::B)),
+                        RF::zero(),
+                        scale.clone(),
+                        self.barrier_thickness.clone(),
+                        RF::one(),
+                        delta_scale.clone(),
+                    )
+                }
+            })
+            .fold(RF::one(), |acc, t| acc.min(t))
+    }
+}
+
+impl Owner {
+    /// The offset of the owner's degrees of freedom in the 24-vector of a pair.
+    pub(super) fn offset(self) -> usize {
+        match self {
+            Owner::A => 0,
+            Owner::B => 12,
+        }
+    }
+}
+
+/// The squared distance between two primitives (a point and a triangle, or two edges), given as
+/// four vertices, along with its gradient and Hessian.
+fn distance_derivatives<RF: RealField>(
+    x: &[Point3<RF>; 4],
+    edge_edge: bool,
+) -> (RF, OVector<RF, Const<12>>, OMatrix<RF, Const<12>, Const<12>>) {
+    if edge_edge {
+        distance_squared::segment_segment_3d_derivatives(&x[0], &x[1], &x[2], &x[3])
+    } else {
+        distance_squared::point_triangle_3d_derivatives(&x[0], &x[1], &x[2], &x[3])
+    }
+}
+
+/// The squared norm of the cross product of the edges `x₀ x₁` and `x₂ x₃`, along with its
+/// gradient and Hessian with respect to the four vertices.
+pub(super) fn edge_cross_derivatives<RF: RealField>(
+    x: &[Point3<RF>; 4],
+) -> (
+    RF,
+    OVector<RF, Const<12>>,
+    OMatrix<RF, Const<12>, Const<12>>,
+) {
+    let two = RF::one() + RF::one();
+    let (u, v) = (&x[1] - &x[0], &x[3] - &x[2]);
+    let (uu, vv, uv) = (u.dot(&u), v.dot(&v), u.dot(&v));
+    let value = uu.clone() * vv.clone() - uv.clone() * uv.clone();
+    //  With respect to the edge vectors `(u, v)`.
+    let du = (u.scale(vv.clone()) - v.scale(uv.clone())).scale(two.clone());
+    let dv = (v.scale(uu.clone()) - u.scale(uv.clone())).scale(two.clone());
+    let identity = Matrix3::<RF>::identity();
+    let duu = (identity.scale(vv) - &v * v.transpose()).scale(two.clone());
+    let dvv = (identity.scale(uu) - &u * u.transpose()).scale(two.clone());
+    let duv = (&u * v.transpose()).scale(two.clone() + two.clone())
+        - (identity.scale(uv) + &v * u.transpose()).scale(two);
+    //  Each edge vector is the difference of its end points.
+    let mut gradient = OVector::<RF, Const<12>>::zeros();
+    for (d, offset) in [(&du, 0), (&dv, 6)] {
+        gradient.fixed_rows_mut::<3>(offset).copy_from(&-d);
+        gradient.fixed_rows_mut::<3>(offset + 3).copy_from(d);
+    }
+    let mut hessian = OMatrix::<RF, Const<12>, Const<12>>::zeros();
+    for (i, j, block) in [(0, 0, &duu), (0, 6, &duv), (6, 6, &dvv)] {
+        for (di, si) in [(0, -RF::one()), (3, RF::one())] {
+            for (dj, sj) in [(0, -RF::one()), (3, RF::one())] {
+                let block = block.scale(si.clone() * sj);
+                hessian
+                    .fixed_view_mut::<3, 3>(i + di, j + dj)
+                    .copy_from(&block);
+                if i != j {
+                    hessian
+                        .fixed_view_mut::<3, 3>(j + dj, i + di)
+                        .copy_from(&block.transpose());
+                }
+            }
+        }
+    }
+    (value, gradient, hessian)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kinematics::contact::{
+        barrier, barrier_derivative, barrier_second_derivative, mollifier, mollifier_derivative,
+        mollifier_second_derivative, Contact, ContactPrimitives,
+    };
+    use crate::kinematics::tests::identity;
+    use crate::kinematics::PairPotential;
+    use crate::AffineTransform;
+    use nalgebra::{point, Const, Matrix3, OVector, Point3, Vector3};
+
+    fn contact() -> Contact<f64> {
+        let mut contact = Contact::new(0.1, 10.);
+        contact.primitives.push(ContactPrimitives::VertexFace(
+            point![0.1, 0.2, -0.05],
+            [point![0., 0., 0.], point![1., 0., 0.], point![0., 1., 0.]],
+        ));
+        contact.primitives.push(ContactPrimitives::FaceVertex(
+            [point![0., 0., 0.], point![1., 0., 0.], point![0., 1., 0.]],
+            point![0.3, 0.3, 0.07],
+        ));
+        contact.primitives.push(ContactPrimitives::EdgeEdge(
+            [point![-1., 0.5, -0.04], point![1., 0.5, -0.04]],
+            [point![0.2, -1., 0.], point![0.2, 1., 0.]],
+        ));
+        contact
+    }
+
+    #[test]
+    fn barrier_derivatives() {
+        let dhat2: f64 = 0.01;
+        let epsilon = 1e-9;
+        for d2 in [1e-4, 1e-3, 5e-3, 9e-3] {
+            let difference =
+                (barrier(d2 + epsilon, dhat2) - barrier(d2 - epsilon, dhat2)) / (2. * epsilon);
+            assert!((difference - barrier_derivative(d2, dhat2)).abs() < 1e-4);
+            let difference = (barrier_derivative(d2 + epsilon, dhat2)
+                - barrier_derivative(d2 - epsilon, dhat2))
+                / (2. * epsilon);
+            assert!(
+                (difference - barrier_second_derivative(d2, dhat2)).abs()
+                    < 1e-4 * difference.abs().max(1.)
+            );
+        }
+        assert_eq!(barrier(0.02, dhat2), 0.);
+        assert_eq!(barrier_derivative(0.02, dhat2), 0.);
+    }
+
+    #[test]
+    fn gradient_matches_finite_differences() {
+        let contact = contact();
+        let a = AffineTransform {
+            translation: Vector3::new(0.01, -0.02, 0.),
+            transform: Matrix3::new(1.01, 0.02, 0., -0.01, 0.99, 0.01, 0., 0.02, 1.),
+        };
+        let b = identity();
+        let (grad_a, grad_b) = contact.gradient(&a, &b);
+        let (grad_a, grad_b) = (
+            OVector::<f64, Const<12>>::from(grad_a),
+            OVector::<f64, Const<12>>::from(grad_b),
+        );
+        let epsilon = 1e-7;
+        for i in 0..12 {
+            let mut plus = OVector::<f64, Const<12>>::from(a.clone());
+            plus[i] += epsilon;
+            let mut minus = OVector::<f64, Const<12>>::from(a.clone());
+            minus[i] -= epsilon;
+            let difference = (contact.potential(&AffineTransform::from(plus), &b)
+                - contact.potential(&AffineTransform::from(minus), &b))
+                / (2. * epsilon);
+            assert!((difference - grad_a[i]).abs() < 1e-4 * grad_a[i].abs().max(1.));
+            let mut plus = OVector::<f64, Const<12>>::from(b.clone());
+            plus[i] += epsilon;
+            let mut minus = OVector::<f64, Const<12>>::from(b.clone());
+            minus[i] -= epsilon;
+            let difference = (contact.potential(&a, &AffineTransform::from(plus))
+                - contact.potential(&a, &AffineTransform::from(minus)))
+                / (2. * epsilon);
+            assert!((difference - grad_b[i]).abs() < 1e-4 * grad_b[i].abs().max(1.));
+        }
+        let hessian = contact.hessian(&a, &b);
+        assert!((hessian - hessian.transpose()).abs().max() < 1e-9);
+        assert!(hessian.symmetric_eigenvalues().min() > -1e-9);
+    }
+
+    #[test]
+    fn step_stops_before_contact() {
+        let mut contact = Contact::new(0.01, 1.);
+        contact.primitives.push(ContactPrimitives::VertexFace(
+            point![0.2, 0.2, 0.5],
+            [point![0., 0., 0.], point![1., 0., 0.], point![0., 1., 0.]],
+        ));
+        let (a, b) = (identity(), identity());
+        let mut da = AffineTransform {
+            translation: Vector3::new(0., 0., -1.),
+            transform: Matrix3::zeros(),
+        };
+        let db = AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::zeros(),
+        };
+        let step = contact.max_step(&a, &da, &b, &db);
+        assert!(0. < step && step < 0.5);
+        da.translation = Vector3::new(0., 0., 1.);
+        assert_eq!(contact.max_step(&a, &da, &b, &db), 1.);
+    }
+
+    #[test]
+    fn mollifier_derivatives() {
+        let threshold: f64 = 0.01;
+        let epsilon = 1e-9;
+        for c in [1e-4, 1e-3, 5e-3, 9e-3] {
+            let difference = (mollifier(c + epsilon, threshold)
+                - mollifier(c - epsilon, threshold))
+                / (2. * epsilon);
+            assert!((difference - mollifier_derivative(c, threshold)).abs() < 1e-4);
+            let difference = (mollifier_derivative(c + epsilon, threshold)
+                - mollifier_derivative(c - epsilon, threshold))
+                / (2. * epsilon);
+            assert!((difference - mollifier_second_derivative(c, threshold)).abs() < 1e-2);
+        }
+        assert_eq!(mollifier(0., threshold), 0.);
+        assert_eq!(mollifier(threshold, threshold), 1.);
+        assert_eq!(mollifier_derivative(threshold, threshold), 0.);
+    }
+
+    #[test]
+    fn mollified_edges_match_finite_differences() {
+        let mut contact = Contact::new(0.1, 10.);
+        //  Almost parallel edges, well within the mollifier threshold.
+        let primitives = ContactPrimitives::EdgeEdge(
+            [point![-1., 0., 0.], point![1., 0., 0.]],
+            [point![-0.9, -0.01, 0.05], point![0.8, 0.01, 0.04]],
+        );
+        let threshold = primitives.mollifier_threshold().unwrap();
+        let x = primitives.world_vertices(&identity(), &identity());
+        assert!(super::edge_cross_derivatives(&x).0 < threshold);
+        let flat = OVector::<f64, Const<12>>::from_iterator(
+            x.iter().flat_map(|p| p.coords.iter().copied()),
+        );
+        let unflat = |flat: &OVector<f64, Const<12>>| {
+            [0, 3, 6, 9].map(|i| Point3::from(flat.fixed_rows::<3>(i).into_owned()))
+        };
+        let (gradient, hessian) = contact.primitive_derivatives(&primitives, &x).unwrap();
+        let epsilon = 1e-7;
+        for i in 0..12 {
+            let mut plus = flat;
+            plus[i] += epsilon;
+            let mut minus = flat;
+            minus[i] -= epsilon;
+            let difference = (contact.primitive_potential(&primitives, &unflat(&plus))
+                - contact.primitive_potential(&primitives, &unflat(&minus)))
+                / (2. * epsilon);
+            assert!((difference - gradient[i]).abs() < 1e-4 * gradient[i].abs().max(1.));
+            let difference = (contact
+                .primitive_derivatives(&primitives, &unflat(&plus))
+                .unwrap()
+                .0
+                - contact
+                    .primitive_derivatives(&primitives, &unflat(&minus))
+                    .unwrap()
+                    .0)
+                / (2. * epsilon);
+            let diff = (difference - hessian.column(i)).abs().max();
+            assert!(diff < 1e-4 * hessian.abs().max().max(1.), "{i}: {diff}");
+        }
+        //  Exactly parallel edges have no energy.
+        contact.primitives.push(ContactPrimitives::EdgeEdge(
+            [point![-1., 0., 0.], point![1., 0., 0.]],
+            [point![-1., 0., 0.05], point![1., 0., 0.05]],
+        ));
+        assert_eq!(contact.potential(&identity(), &identity()), 0.);
+    }
+}
@@ -0,0 +1,226 @@
+//! Physical loads as the generalized forces of [`ObjectRef::forces`], and simple
+//! [`ForceField`]s.
+//!
+//! A generalized force `f` is the affine transform whose 12-vector is `Jᵀ F`, summed over the
+//! loads `F` applied at the body space points `x` (see [`AffineTransform::jacobian`]).  Forces
+//! add up, so several loads on the same body are simply summed.
+
+use crate::kinematics::{ForceField, ObjectRef};
+use crate::{AffineTransform, Moments};
+
+/// The generalized force of the uniform acceleration `gravity` (such as `(0, 0, -9.81)`) on a body
+/// with the given moments and density.  It only involves the volume and the first moments, and
+/// accelerates every point of the body by `gravity`, whatever the center of mass.
+pub fn gravity<RF: RealField>(
+    moments: &Moments<RF>,
+    density: RF,
+    gravity: &Vector3<RF>,
+) -> AffineTransform<RF> {
+    let first = moments.first();
+    AffineTransform {
+        translation: gravity.scale(moments.v.clone() * density.clone()),
+        transform: (gravity * first.transpose()).scale(density),
+    }
+}
+
+/// The generalized force of `force` applied at the body space point `x`.
+pub fn point_force<RF: RealField>(x: &Point3<RF>, force: &Vector3<RF>) -> AffineTransform<RF> {
+    AffineTransform::from(AffineTransform::jacobian(x).transpose() * force)
+}
+
+/// The generalized force of the pure (world space) torque `torque` on a body at `state`,
+/// `½ [τ]ₓ A`.  It does the work `τ · δθ` on an infinitesimal rotation `δθ` of a rigid body,
+/// and is recomputed from the state at the start of every time step.
+pub fn torque<RF: RealField>(
+    state: &AffineTransform<RF>,
+    torque: &Vector3<RF>,
+) -> AffineTransform<RF> {
+    let half = (RF::one() + RF::one()).recip();
+    AffineTransform {
+        translation: Vector3::zeros(),
+        transform: (torque.cross_matrix() * &state.transform).scale(half),
+    }
+}
+
+/// A zero length spring pulling the body space point `point` towards the world space `target`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetSpring<RF: RealField> {
+    /// The body space point that is pulled.
+    pub point: Point3<RF>,
+    /// Where the point is pulled to.
+    pub target: Point3<RF>,
+    /// The stiffness of the spring.
+    pub stiffness: RF,
+}
+
+impl<RF: RealField> ForceField<RF> for TargetSpring<RF> {
+    fn potential(&self, q: &AffineTransform<RF>) -> RF {
+        let half = (RF::one() + RF::one()).recip();
+        (q.transform_point(&self.point) - &self.target).norm_squared()
+            * self.stiffness.clone()
+            * half
+    }
+
+    fn gradient(&self, q: &AffineTransform<RF>) -> AffineTransform<RF> {
+        let stretch = (q.transform_point(&self.point) - &self.target).scale(self.stiffness.clone());
+        point_force(&self.point, &stretch)
+    }
+
+    fn hessian(&self, _: &AffineTransform<RF>) -> OMatrix<RF, Const<12>, Const<12>> {
+        let jacobian = AffineTransform::jacobian(&self.point);
+        jacobian.tr_mul(&jacobian).scale(self.stiffness.clone())
+    }
+}
+
+impl<'s, 'v, 'm, 'f, RF: RealField> ObjectRef<'s, 'v, 'm, 'f, RF> {
+    /// The generalized force of the uniform acceleration `gravity` on this object, see
+    /// [`gravity`].
+    pub fn gravity(&self, gravity: &Vector3<RF>) -> AffineTransform<RF> {
+        self::gravity(self.moments, self.density.clone(), gravity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kinematics::forces::{gravity, point_force, torque, TargetSpring};
+    use crate::kinematics::tests::{
+        at_rest, identity, random_transform, unit_cube, zero, Fixed, Spring,
+    };
+    use crate::kinematics::{ForceField, InteractingObjects, NewtonSolver, ObjectRef};
+    use crate::{AffineTransform, Moments};
+    use hashbrown::HashMap;
+    use nalgebra::{point, Const, Matrix3, OVector, Rotation3, Unit, Vector3};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// A unit cube centered on `(1, 2, 3)`.
+    fn shifted_cube() -> Moments<f64> {
+        let (c, cube) = (Vector3::new(1., 2., 3.), unit_cube());
+        Moments {
+            x: c.x,
+            y: c.y,
+            z: c.z,
+            xx: cube.xx + c.x * c.x,
+            xy: c.x * c.y,
+            xz: c.x * c.z,
+            yy: cube.yy + c.y * c.y,
+            yz: c.y * c.z,
+            zz: cube.zz + c.z * c.z,
+            ..cube
+        }
+    }
+
+    #[test]
+    fn gravity_accelerates_uniformly() {
+        let (state, moments, zero) = (identity(), shifted_cube(), zero());
+        let g = Vector3::new(0., 0., -9.81);
+        let forces = gravity(&moments, 3., &g);
+        let object = ObjectRef {
+            state: &state,
+            velocity: &zero,
+            moments: &moments,
+            forces: &forces,
+            density: 3.,
+        };
+        assert_eq!(object.gravity(&g), forces);
+        let predicted = object.predicted_state(0.1);
+        assert!((predicted.translation - g * 0.01).norm() < 1e-12);
+        assert!((predicted.transform - Matrix3::identity()).norm() < 1e-12);
+        //  A force at the center of mass does the same.
+        let pushed = point_force(&point![1., 2., 3.], &g.scale(3.));
+        assert!((pushed.to_matrix() - forces.to_matrix()).norm() < 1e-12);
+    }
+
+    #[test]
+    fn torque_does_rotational_work() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let tau = Vector3::new(0.3, -1., 2.);
+        let rotation =
+            Rotation3::from_axis_angle(&Unit::new_normalize(Vector3::new(1., 1., 0.)), 1.);
+        let state = AffineTransform {
+            translation: Vector3::new(4., 5., 6.),
+            transform: rotation.into_inner(),
+        };
+        let force = torque(&state, &tau);
+        for _ in 0..4 {
+            let theta = random_transform(&mut rng).translation;
+            let virtual_rotation = theta.cross_matrix() * state.transform;
+            assert!((force.transform.dot(&virtual_rotation) - tau.dot(&theta)).abs() < 1e-12);
+        }
+        //  Matches the rotational part of a couple.
+        let r = Vector3::new(0.5, 0.1, -0.2);
+        let f = Vector3::new(-1., 0.4, 0.7);
+        let couple = point_force(&r.into(), &f) + point_force(&(-r).into(), &-f);
+        let difference = couple.transform - torque(&identity(), &(2. * r.cross(&f))).transform;
+        assert!((difference - difference.transpose()).norm() < 1e-12);
+    }
+
+    #[test]
+    fn target_spring_derivatives() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let spring = TargetSpring {
+            point: point![0.2, -0.4, 0.5],
+            target: point![1., 1., -2.],
+            stiffness: 7.,
+        };
+        let q = random_transform(&mut rng);
+        let gradient = OVector::<f64, Const<12>>::from(spring.gradient(&q));
+        let hessian = spring.hessian(&q);
+        let epsilon = 1e-6;
+        for i in 0..12 {
+            let mut plus = OVector::<f64, Const<12>>::from(q.clone());
+            plus[i] += epsilon;
+            let mut minus = OVector::<f64, Const<12>>::from(q.clone());
+            minus[i] -= epsilon;
+            let (plus, minus) = (AffineTransform::from(plus), AffineTransform::from(minus));
+            let difference = (spring.potential(&plus) - spring.potential(&minus)) / (2. * epsilon);
+            assert!((difference - gradient[i]).abs() < 1e-6);
+            let difference = (OVector::<f64, Const<12>>::from(spring.gradient(&plus))
+                - OVector::<f64, Const<12>>::from(spring.gradient(&minus)))
+                / (2. * epsilon);
+            assert!((difference - hessian.column(i)).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn projected_hessian_clamps_fields() {
+        let (state, moments, zero) = (identity(), unit_cube(), zero());
+        let objects = vec![at_rest(&state, &moments, &zero)];
+        //  Pushes the object away, which no mass can hold back at this time step.
+        let spring = TargetSpring {
+            point: point![0., 0., 0.],
+            target: point![0., 0., 0.],
+            stiffness: -1e4,
+        };
+        let objects: InteractingObjects<_, Spring> =
+            InteractingObjects::new(objects, HashMap::new(), 1e3).with_force_field(0, &spring);
+        let param = [identity()];
+        let hessian = objects.hessian(&param, 0.1).to_dense();
+        let projected = objects.projected_hessian(&param, 0.1).to_dense();
+        assert!(hessian[(0, 0)] < 0.);
+        assert!((projected[(0, 0)] - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fields_follow_objects_into_isles() {
+        let (state, moments, zero) = (identity(), unit_cube(), zero());
+        let objects = Vec::from_iter((0..2).map(|_| at_rest(&state, &moments, &zero)));
+        let spring = TargetSpring {
+            point: point![0., 0., 0.],
+            target: point![0., 0., 1.],
+            stiffness: 1e4,
+        };
+        let objects: InteractingObjects<_, Spring> =
+            InteractingObjects::new(objects, HashMap::new(), 1e3).with_force_field(1, &spring);
+        let isles = objects.create_isles(&Fixed(vec![], vec![]), (), |part| part.0);
+        assert_eq!(isles.len(), 2);
+        let reports = Result::<Vec<_>, _>::from_iter(
+            NewtonSolver::default().solve_isles(&isles, 0.1),
+        )
+        .unwrap();
+        assert_eq!(reports[0].state[0], identity());
+        //  Pulled most of the way, held back by the inertia.
+        let z = reports[1].state[0].translation.z;
+        assert!((z - 1e2 / (1. + 1e2)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,437 @@
+//! Lagged incremental potential contact friction between affine bodies.
+
+use crate::collision_detection::closest_point;
+use crate::collision_detection::distance_type::{
+    self, EdgeEdgeDistanceType, PointTriangleDistanceType,
+};
+use crate::kinematics::contact::{
+    barrier_derivative, edge_cross_derivatives, mollifier, Contact, ContactPrimitives,
+};
+use crate::kinematics::PairPotential;
+use crate::AffineTransform;
+
+/// The lagged dissipative friction between the primitives of a [`Contact`].  The contact normal
+/// forces, tangent bases and closest points are all fixed ("lagged") at the start of the time
+/// step, which makes the friction a potential in the states at the end of the step.  It should be
+/// rebuilt with [`Friction::lagged`] every time step, and is usually paired with its contact, as
+/// `(contact, friction)`.
+#[derive(Clone, Debug)]
+pub struct Friction<RF: RealField> {
+    /// The coefficient of friction `μ` between the two objects.
+    pub coefficient: RF,
+    /// The tangential displacement `ε_v h` below which the objects are considered to be sticking.
+    /// The friction force is smoothly ramped up to its full value over this displacement.
+    pub threshold: RF,
+    /// The states of the objects at the start of the time step.
+    pub start: (AffineTransform<RF>, AffineTransform<RF>),
+    contacts: Vec<LaggedContact<RF>>,
+}
+
+/// The lagged quantities of a single pair of primitives.
+#[derive(Clone, Debug)]
+struct LaggedContact<RF: RealField> {
+    /// The magnitude of the contact force `λ`.
+    normal_force: RF,
+    /// Maps the displacements of both objects (as a 24-vector) to the relative tangential
+    /// displacement of the closest points.
+    tangent: OMatrix<RF, Const<2>, Const<24>>,
+}
+
+/// The IPC smoothed friction magnitude `f₀`, which is the displacement `x` itself beyond
+/// `threshold`, and a cubic below it.  Its derivative is continuous, and zero at zero.
+pub fn smooth_friction<RF: RealField>(x: RF, threshold: RF) -> RF {
+    if x >= threshold {
+        x
+    } else {
+        let three = RF::one() + RF::one() + RF::one();
+        let ratio = x.clone() / threshold.clone();
+        let square = x * ratio.clone();
+        (threshold + square.clone() * (three.clone() - ratio)) / three
+    }
+}
+
+/// The derivative `f₁` of [`smooth_friction`], which ramps up from zero to one over the threshold.
+pub fn smooth_friction_derivative<RF: RealField>(x: RF, threshold: RF) -> RF {
+    if x >= threshold {
+        RF::one()
+    } else {
+        let ratio = x / threshold;
+        (RF::one() + RF::one() - ratio.clone()) * ratio
+    }
+}
+
+impl<RF: RealField> Friction<RF> {
+    /// Lags the active primitive pairs of `contact`, with the objects at `a` and `b` at the start
+    /// of the time step.
+    pub fn lagged(
+        contact: &Contact<RF>,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+        coefficient: RF,
+        threshold: RF,
+    ) -> Self {
+        let two = RF::one() + RF::one();
+        let dhat2 = contact.barrier_thickness.clone() * contact.barrier_thickness.clone();
+        let mut contacts = Vec::new();
+        for primitives in &contact.primitives {
+            let x = primitives.world_vertices(a, b);
+            let d2 = primitives.distance_squared(a, b);
+            if d2 >= dhat2 || d2.is_zero() {
+                continue;
+            }
+            //  λ = -κ ∂b/∂d, which is the magnitude of the barrier force.
+            let mut normal_force = -barrier_derivative(d2.clone(), dhat2.clone())
+                * two.clone()
+                * d2.sqrt()
+                * contact.stiffness.clone();
+            if let Some(threshold) = primitives.mollifier_threshold() {
+                normal_force *= mollifier(edge_cross_derivatives(&x).0, threshold);
+            }
+            if normal_force.is_zero() {
+                continue;
+            }
+            let (weights, basis) = closest_points(primitives, &x);
+            let mut tangent = OMatrix::<RF, Const<2>, Const<24>>::zeros();
+            for ((owner, x), weight) in primitives.vertices().into_iter().zip(weights) {
+                let mut view = tangent.fixed_view_mut::<2, 12>(0, owner.offset());
+                view += (basis.transpose() * AffineTransform::jacobian(x)).scale(weight);
+            }
+            contacts.push(LaggedContact {
+                normal_force,
+                tangent,
+            });
+        }
+        Self {
+            coefficient,
+            threshold,
+            start: (a.clone(), b.clone()),
+            contacts,
+        }
+    }
+
+    /// The number of lagged primitive pairs.
+    pub fn len(&self) -> usize {
+        self.contacts.len()
+    }
+
+    /// Whether there are no lagged primitive pairs, in which case there is no friction.
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty()
+    }
+
+    /// The displacement of both objects from the start of the time step, as a 24-vector.
+    fn displacement(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> OVector<RF, Const<24>> {
+        let mut displacement = OVector::<RF, Const<24>>::zeros();
+        displacement
+            .fixed_rows_mut::<12>(0)
+            .copy_from(&OVector::<RF, Const<12>>::from(
+                a.clone() - self.start.0.clone(),
+            ));
+        displacement
+            .fixed_rows_mut::<12>(12)
+            .copy_from(&OVector::<RF, Const<12>>::from(
+                b.clone() - self.start.1.clone(),
+            ));
+        displacement
+    }
+
+    /// `f₁(|u|) / |u|`, which has a finite limit at zero.
+    fn force_ratio(&self, norm: RF) -> RF {
+        if norm >= self.threshold {
+            norm.recip()
+        } else {
+            let two = RF::one() + RF::one();
+            (two - norm / self.threshold.clone()) / self.threshold.clone()
+        }
+    }
+}
+
+impl<RF: RealField> PairPotential<RF> for Friction<RF> {
+    fn potential(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> RF {
+        let displacement = self.displacement(a, b);
+        self.contacts
+            .iter()
+            .map(|contact| {
+                let u = &contact.tangent * &displacement;
+                contact.normal_force.clone() * smooth_friction(u.norm(), self.threshold.clone())
+            })
+            .fold(RF::zero(), |acc, e| acc + e)
+            * self.coefficient.clone()
+    }
+
+    fn gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>) {
+        let displacement = self.displacement(a, b);
+        let mut gradient = OVector::<RF, Const<24>>::zeros();
+        for contact in &self.contacts {
+            let u = &contact.tangent * &displacement;
+            let scale = self.coefficient.clone()
+                * contact.normal_force.clone()
+                * self.force_ratio(u.norm());
+            gradient += contact.tangent.transpose() * u.scale(scale);
+        }
+        (
+            AffineTransform::from(gradient.fixed_rows::<12>(0)),
+            AffineTransform::from(gradient.fixed_rows::<12>(12)),
+        )
+    }
+
+    fn hessian(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> OMatrix<RF, Const<24>, Const<24>> {
+        let displacement = self.displacement(a, b);
+        let mut hessian = OMatrix::<RF, Const<24>, Const<24>>::zeros();
+        for contact in &self.contacts {
+            let u = &contact.tangent * &displacement;
+            let norm = u.norm();
+            //  With respect to `u`, this is `f₁'(|u|) ûûᵀ + f₁(|u|) / |u| (I - ûûᵀ)`, which is
+            //  positive semi-definite.
+            let inner = if norm >= self.threshold {
+                (Matrix2::identity()
+                    - (&u * u.transpose()).scale((norm.clone() * norm.clone()).recip()))
+                .scale(norm.recip())
+            } else if norm.is_zero() {
+                Matrix2::identity().scale(self.force_ratio(norm))
+            } else {
+                let ramp = self.threshold.clone() * self.threshold.clone() * norm.clone();
+                Matrix2::identity().scale(self.force_ratio(norm))
+                    - (&u * u.transpose()).scale(ramp.recip())
+            };
+            let scale = self.coefficient.clone() * contact.normal_force.clone();
+            hessian += contact.tangent.transpose() * inner.scale(scale) * &contact.tangent;
+        }
+        hessian
+    }
+}
+
+/// The weights of the closest points of the primitives (positive for the vertices of `a`, and
+/// negative for those of `b`, in the order of [`ContactPrimitives::vertices`]), and the tangent
+/// basis of the contact, which depends on which features are closest.
+fn closest_points<RF: RealField>(
+    primitives: &ContactPrimitives<RF>,
+    x: &[Point3<RF>; 4],
+) -> ([RF; 4], OMatrix<RF, Const<3>, Const<2>>) {
+    let l = RF::one();
+    match primitives {
+        ContactPrimitives::VertexFace(..) | ContactPrimitives::FaceVertex(..) => {
+            let [p, t0, t1, t2] = x;
+            let (_, [w0, w1, w2]) = closest_point::on_triangle_3d_barycentric(p, t0, t1, t2);
+            let basis = match distance_type::point_triangle(p, t0, t1, t2) {
+                PointTriangleDistanceType::PT => edge_edge_basis(t0, t1, t0, t2),
+                PointTriangleDistanceType::PAB => point_edge_basis(p, t0, t1),
+                PointTriangleDistanceType::PBC => point_edge_basis(p, t1, t2),
+                PointTriangleDistanceType::PCA => point_edge_basis(p, t2, t0),
+                PointTriangleDistanceType::PA => point_point_basis(p, t0),
+                PointTriangleDistanceType::PB => point_point_basis(p, t1),
+                PointTriangleDistanceType::PC => point_point_basis(p, t2),
+            };
+            //  The relative displacement is always that of `a` less that of `b`.
+            let sign = if matches!(primitives, ContactPrimitives::VertexFace(..)) {
+                l
+            } else {
+                -l
+            };
+            (
+                [
+                    sign.clone(),
+                    -sign.clone() * w0,
+                    -sign.clone() * w1,
+                    -sign * w2,
+                ],
+                basis,
+            )
+        }
+        ContactPrimitives::EdgeEdge(..) => {
+            use EdgeEdgeDistanceType::*;
+            let [a0, a1, b0, b1] = x;
+            let (s, t) = closest_point::segment_segment_parameters_3d(a0, a1, b0, b1);
+            let basis = match distance_type::edge_edge(a0, a1, b0, b1) {
+                AB => edge_edge_basis(a0, a1, b0, b1),
+                AB0 => point_edge_basis(b0, a0, a1),
+                AB1 => point_edge_basis(b1, a0, a1),
+                A0B => point_edge_basis(a0, b0, b1),
+                A1B => point_edge_basis(a1, b0, b1),
+                A0B0 => point_point_basis(a0, b0),
+                A0B1 => point_point_basis(a0, b1),
+                A1B0 => point_point_basis(a1, b0),
+                A1B1 => point_point_basis(a1, b1),
+            };
+            ([l.clone() - s.clone(), s, t.clone() - l, -t], basis)
+        }
+    }
+}
+
+/// An orthonormal basis of the plane spanned by the edges `a0 a1` and `b0 b1`.
+fn edge_edge_basis<RF: RealField>(
+    a0: &Point3<RF>,
+    a1: &Point3<RF>,
+    b0: &Point3<RF>,
+    b1: &Point3<RF>,
+) -> OMatrix<RF, Const<3>, Const<2>> {
+    let e0 = (a1 - a0).normalize();
+    let normal = (a1 - a0).cross(&(b1 - b0));
+    OMatrix::<RF, Const<3>, Const<2>>::from_columns(&[e0.clone(), normal.cross(&e0).normalize()])
+}
+
+/// An orthonormal basis perpendicular to the closest offset between `p` and the edge `a b`.
+fn point_edge_basis<RF: RealField>(
+    p: &Point3<RF>,
+    a: &Point3<RF>,
+    b: &Point3<RF>,
+) -> OMatrix<RF, Const<3>, Const<2>> {
+    let e0 = (b - a).normalize();
+    let e1 = e0.cross(&(p - a)).normalize();
+    OMatrix::<RF, Const<3>, Const<2>>::from_columns(&[e0, e1])
+}
+
+/// An orthonormal basis perpendicular to the offset between `p` and `q`.
+fn point_point_basis<RF: RealField>(
+    p: &Point3<RF>,
+    q: &Point3<RF>,
+) -> OMatrix<RF, Const<3>, Const<2>> {
+    let normal = (p - q).normalize();
+    //  Cross with the axis least aligned with the normal.
+    let axis = normal.iamin();
+    let mut other = Vector3::zeros();
+    other[axis] = RF::one();
+    let e0 = normal.cross(&other).normalize();
+    let e1 = normal.cross(&e0);
+    OMatrix::<RF, Const<3>, Const<2>>::from_columns(&[e0, e1])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kinematics::contact::{Contact, ContactPrimitives};
+    use crate::kinematics::friction::{smooth_friction, smooth_friction_derivative, Friction};
+    use crate::kinematics::tests::identity;
+    use crate::kinematics::PairPotential;
+    use crate::AffineTransform;
+    use nalgebra::{point, Const, Matrix3, OVector, Vector3};
+
+    fn contact() -> Contact<f64> {
+        let mut contact = Contact::new(0.1, 10.);
+        contact.primitives.push(ContactPrimitives::VertexFace(
+            point![0.1, 0.2, 0.05],
+            [point![0., 0., 0.], point![1., 0., 0.], point![0., 1., 0.]],
+        ));
+        contact.primitives.push(ContactPrimitives::FaceVertex(
+            [point![0., 0., 0.], point![1., 0., 0.], point![0., 1., 0.]],
+            point![0.6, 0.45, -0.03],
+        ));
+        contact.primitives.push(ContactPrimitives::EdgeEdge(
+            [point![-1., 0.5, 0.04], point![1., 0.5, 0.04]],
+            [point![0.2, -1., 0.], point![0.2, 1., 0.]],
+        ));
+        contact
+    }
+
+    #[test]
+    fn smooth_friction_derivatives() {
+        let threshold: f64 = 0.01;
+        let epsilon = 1e-9;
+        for x in [0., 1e-3, 5e-3, 9e-3, 2e-2] {
+            let difference = (smooth_friction(x + epsilon, threshold)
+                - smooth_friction(x - epsilon, threshold))
+                / (2. * epsilon);
+            assert!((difference - smooth_friction_derivative(x, threshold)).abs() < 1e-6);
+        }
+        assert!((smooth_friction(threshold, threshold) - threshold).abs() < 1e-15);
+        assert_eq!(smooth_friction_derivative(0., threshold), 0.);
+        assert_eq!(smooth_friction_derivative(threshold, threshold), 1.);
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let friction = Friction::lagged(&contact(), &identity(), &identity(), 0.5, 1e-2);
+        assert_eq!(friction.len(), 3);
+        let a = AffineTransform {
+            translation: Vector3::new(0.004, -0.003, 0.001),
+            transform: Matrix3::new(1.01, 0.002, 0., -0.001, 0.999, 0.001, 0., 0.002, 1.),
+        };
+        let b = AffineTransform {
+            translation: Vector3::new(-0.01, 0.002, 0.),
+            transform: Matrix3::identity(),
+        };
+        let flat = |a: &AffineTransform<f64>, b: &AffineTransform<f64>| {
+            OVector::<f64, Const<24>>::from_iterator(
+                OVector::<f64, Const<12>>::from(a.clone())
+                    .iter()
+                    .chain(OVector::<f64, Const<12>>::from(b.clone()).iter())
+                    .copied(),
+            )
+        };
+        let unflat = |x: &OVector<f64, Const<24>>| {
+            (
+                AffineTransform::from(x.fixed_rows::<12>(0).into_owned()),
+                AffineTransform::from(x.fixed_rows::<12>(12).into_owned()),
+            )
+        };
+        let x = flat(&a, &b);
+        let (grad_a, grad_b) = friction.gradient(&a, &b);
+        let gradient = flat(&grad_a, &grad_b);
+        let hessian = friction.hessian(&a, &b);
+        let epsilon = 1e-7;
+        for i in 0..24 {
+            let mut plus = x;
+            plus[i] += epsilon;
+            let mut minus = x;
+            minus[i] -= epsilon;
+            let ((plus_a, plus_b), (minus_a, minus_b)) = (unflat(&plus), unflat(&minus));
+            let difference = (friction.potential(&plus_a, &plus_b)
+                - friction.potential(&minus_a, &minus_b))
+                / (2. * epsilon);
+            assert!(
+                (difference - gradient[i]).abs() < 1e-5 * gradient[i].abs().max(1.),
+                "{i}: {difference} != {}",
+                gradient[i]
+            );
+            let (plus_a, plus_b) = friction.gradient(&plus_a, &plus_b);
+            let (minus_a, minus_b) = friction.gradient(&minus_a, &minus_b);
+            let difference = (flat(&plus_a, &plus_b) - flat(&minus_a, &minus_b)) / (2. * epsilon);
+            let diff = (difference - hessian.column(i)).abs().max();
+            assert!(diff < 1e-4 * hessian.abs().max().max(1.), "{i}: {diff}");
+        }
+        assert!(hessian.symmetric_eigenvalues().min() > -1e-9);
+    }
+
+    #[test]
+    fn opposes_sliding() {
+        let mut contact = Contact::new(0.1, 10.);
+        contact.primitives.push(ContactPrimitives::VertexFace(
+            point![0.2, 0.2, 0.05],
+            [point![0., 0., 0.], point![1., 0., 0.], point![0., 1., 0.]],
+        ));
+        let friction = Friction::lagged(&contact, &identity(), &identity(), 0.5, 1e-3);
+        //  Moving along the normal is frictionless.
+        let lifted = AffineTransform {
+            translation: Vector3::new(0., 0., 0.01),
+            transform: Matrix3::identity(),
+        };
+        assert_eq!(
+            friction.potential(&lifted, &identity()),
+            friction.potential(&identity(), &identity())
+        );
+        assert_eq!(
+            friction.gradient(&lifted, &identity()).0.translation,
+            Vector3::zeros()
+        );
+        //  Sliding beyond the threshold is resisted by the full force, `μ λ`.
+        let slid = AffineTransform {
+            translation: Vector3::new(0.01, 0., 0.),
+            transform: Matrix3::identity(),
+        };
+        let (grad_a, grad_b) = friction.gradient(&slid, &identity());
+        let normal_force = friction.contacts[0].normal_force;
+        assert!((grad_a.translation - Vector3::new(0.5 * normal_force, 0., 0.)).norm() < 1e-9);
+        assert!((grad_b.translation + grad_a.translation).norm() < 1e-9);
+    }
+}
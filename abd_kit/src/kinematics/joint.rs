@@ -0,0 +1,413 @@
+//! Joints that link material points and directions of two affine bodies.
+
+use crate::kinematics::PairPotential;
+use crate::AffineTransform;
+
+/// A joint between two affine bodies, such as a ball-socket or a hinge.
+///
+/// Every joint is a set of constraints `C(a, b) = 0` that are linear in the 12-vectors of the
+/// bodies, since the world position `p + A x` of a body space point `x` (and the world direction
+/// `A d` of a body space direction `d`) is.  The constraints are enforced through the augmented
+/// Lagrangian energy `½ k |C|² + λᵀ C`, which is quadratic with a constant Hessian.  With `λ`
+/// left at zero this is a plain stiff penalty; calling [`Self::update_multiplier`] after every
+/// time step makes the joint converge to an exact one without having to raise `k`.
+///
+/// Two bodies that are both jointed and in contact are linked by a pair of potentials, as in
+/// `(contact, joint)`.
+#[derive(Clone, Debug)]
+pub struct Joint<RF: RealField> {
+    /// The penalty stiffness `k`.  Something on the order of the mass of the lighter body
+    /// divided by the square of the time step keeps the drift small.
+    pub stiffness: RF,
+    /// The Lagrange multiplier estimates `λ`, one per constraint.
+    pub multiplier: DVector<RF>,
+    /// Maps the 12-vectors of both bodies (as a 24-vector) to the constraints.
+    constraints: OMatrix<RF, Dyn, Const<24>>,
+}
+
+impl<RF: RealField> Joint<RF> {
+    fn with_constraints(constraints: OMatrix<RF, Dyn, Const<24>>, stiffness: RF) -> Self {
+        Self {
+            stiffness,
+            multiplier: DVector::zeros(constraints.nrows()),
+            constraints,
+        }
+    }
+
+    /// Keeps the body space point `anchor_a` of the first body on `anchor_b` of the second,
+    /// leaving the bodies free to rotate about it.
+    pub fn ball_socket(anchor_a: Point3<RF>, anchor_b: Point3<RF>, stiffness: RF) -> Self {
+        let mut constraints = OMatrix::<RF, Dyn, Const<24>>::zeros(3);
+        pin(&mut constraints, 0, &anchor_a, &anchor_b);
+        Self::with_constraints(constraints, stiffness)
+    }
+
+    /// A ball-socket at the anchors that also keeps the body space direction `axis_a` of the
+    /// first body on `axis_b` of the second, leaving the bodies free to rotate about the axis.
+    /// The axes are not normalized; the alignment is as stiff as pinning the far end of the
+    /// axis, so they are best given the length of the hinge.
+    ///
+    /// Returns `None` if either axis is zero, which would leave a ball-socket.
+    pub fn hinge(
+        anchor_a: Point3<RF>,
+        anchor_b: Point3<RF>,
+        axis_a: Vector3<RF>,
+        axis_b: Vector3<RF>,
+        stiffness: RF,
+    ) -> Option<Self> {
+        axis_a.try_normalize(RF::default_epsilon())?;
+        axis_b.try_normalize(RF::default_epsilon())?;
+        let mut constraints = OMatrix::<RF, Dyn, Const<24>>::zeros(6);
+        pin(&mut constraints, 0, &anchor_a, &anchor_b);
+        align(&mut constraints, 3, &axis_a, &axis_b);
+        Some(Self::with_constraints(constraints, stiffness))
+    }
+
+    /// Welds the bodies together, keeping `anchor_a` on `anchor_b` and each column of the body
+    /// space frame `frame_a` of the first body on that of `frame_b` of the second.
+    pub fn fixed(
+        anchor_a: Point3<RF>,
+        anchor_b: Point3<RF>,
+        frame_a: Matrix3<RF>,
+        frame_b: Matrix3<RF>,
+        stiffness: RF,
+    ) -> Self {
+        let mut constraints = OMatrix::<RF, Dyn, Const<24>>::zeros(12);
+        pin(&mut constraints, 0, &anchor_a, &anchor_b);
+        for j in 0..3 {
+            align(
+                &mut constraints,
+                3 + 3 * j,
+                &frame_a.column(j).into_owned(),
+                &frame_b.column(j).into_owned(),
+            );
+        }
+        Self::with_constraints(constraints, stiffness)
+    }
+
+    /// Keeps the frames of the bodies aligned, as [`Self::fixed`] does, while letting `anchor_b`
+    /// slide along the first column of `frame_a` through `anchor_a`.
+    ///
+    /// Staying on a line that turns with the first body is not linear in the 12-vectors, so the
+    /// directions across the line are lagged at `a`, the state of the first body at the start
+    /// of the time step.  Like [`Friction`](crate::kinematics::friction::Friction), the joint
+    /// should be rebuilt every time step, carrying over its [`Self::multiplier`].
+    ///
+    /// Returns `None` if the second or third column of `frame_a` is zero, or is turned into zero
+    /// by `a`, as then there is no direction across the line to keep `anchor_b` from moving in.
+    pub fn slider(
+        anchor_a: Point3<RF>,
+        anchor_b: Point3<RF>,
+        frame_a: Matrix3<RF>,
+        frame_b: Matrix3<RF>,
+        a: &AffineTransform<RF>,
+        stiffness: RF,
+    ) -> Option<Self> {
+        let mut constraints = OMatrix::<RF, Dyn, Const<24>>::zeros(11);
+        for j in 0..3 {
+            align(
+                &mut constraints,
+                3 * j,
+                &frame_a.column(j).into_owned(),
+                &frame_b.column(j).into_owned(),
+            );
+        }
+        let mut offset = OMatrix::<RF, Dyn, Const<24>>::zeros(3);
+        pin(&mut offset, 0, &anchor_a, &anchor_b);
+        for j in 1..3 {
+            let normal = a
+                .transform_vector(&frame_a.column(j).into_owned())
+                .try_normalize(RF::default_epsilon())?;
+            let row = normal.transpose() * offset.fixed_rows::<3>(0);
+            constraints.row_mut(8 + j).copy_from(&row);
+        }
+        Some(Self::with_constraints(constraints, stiffness))
+    }
+
+    /// The number of constraints.
+    pub fn len(&self) -> usize {
+        self.constraints.nrows()
+    }
+
+    /// Whether the joint has no constraints, in which case it does nothing.
+    pub fn is_empty(&self) -> bool {
+        self.constraints.nrows() == 0
+    }
+
+    /// The constraints `C` when the bodies are at `a` and `b`, which are all zero when the joint
+    /// holds.
+    pub fn violation(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> DVector<RF> {
+        &self.constraints * stacked(a, b)
+    }
+
+    /// The augmented Lagrangian update `λ ← λ + k C`, with the bodies at their states `a` and
+    /// `b` at the end of the time step.
+    pub fn update_multiplier(&mut self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) {
+        let violation = self.violation(a, b);
+        self.multiplier += violation.scale(self.stiffness.clone());
+    }
+}
+
+impl<RF: RealField> PairPotential<RF> for Joint<RF> {
+    fn potential(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> RF {
+        let violation = self.violation(a, b);
+        let half = (RF::one() + RF::one()).recip();
+        violation.norm_squared() * self.stiffness.clone() * half + self.multiplier.dot(&violation)
+    }
+
+    fn gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>) {
+        let force = self.violation(a, b).scale(self.stiffness.clone()) + &self.multiplier;
+        let gradient = self.constraints.tr_mul(&force);
+        (
+            AffineTransform::from(gradient.fixed_rows::<12>(0)),
+            AffineTransform::from(gradient.fixed_rows::<12>(12)),
+        )
+    }
+
+    fn hessian(
+        &self,
+        _: &AffineTransform<RF>,
+        _: &AffineTransform<RF>,
+    ) -> OMatrix<RF, Const<24>, Const<24>> {
+        self.constraints
+            .tr_mul(&self.constraints)
+            .scale(self.stiffness.clone())
+    }
+}
+
+/// The 12-vectors of both bodies as a 24-vector.
+fn stacked<RF: RealField>(
+    a: &AffineTransform<RF>,
+    b: &AffineTransform<RF>,
+) -> OVector<RF, Const<24>> {
+    let mut stacked = OVector::<RF, Const<24>>::zeros();
+    stacked
+        .fixed_rows_mut::<12>(0)
+        .copy_from(&OVector::<RF, Const<12>>::from(a.clone()));
+    stacked
+        .fixed_rows_mut::<12>(12)
+        .copy_from(&OVector::<RF, Const<12>>::from(b.clone()));
+    stacked
+}
+
+/// Writes the three constraints `(p_a + A_a x_a) - (p_b + A_b x_b)` from `row` on.
+fn pin<RF: RealField>(
+    constraints: &mut OMatrix<RF, Dyn, Const<24>>,
+    row: usize,
+    x_a: &Point3<RF>,
+    x_b: &Point3<RF>,
+) {
+    constraints
+        .fixed_view_mut::<3, 12>(row, 0)
+        .copy_from(&AffineTransform::jacobian(x_a));
+    constraints
+        .fixed_view_mut::<3, 12>(row, 12)
+        .copy_from(&-AffineTransform::jacobian(x_b));
+}
+
+/// Writes the three constraints `A_a d_a - A_b d_b` from `row` on.
+fn align<RF: RealField>(
+    constraints: &mut OMatrix<RF, Dyn, Const<24>>,
+    row: usize,
+    d_a: &Vector3<RF>,
+    d_b: &Vector3<RF>,
+) {
+    pin(
+        constraints,
+        row,
+        &Point3::from(d_a.clone()),
+        &Point3::from(d_b.clone()),
+    );
+    //  Directions do not move with the translations.
+    constraints.fixed_view_mut::<3, 3>(row, 0).fill(RF::zero());
+    constraints.fixed_view_mut::<3, 3>(row, 12).fill(RF::zero());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kinematics::joint::Joint;
+    use crate::kinematics::tests::{identity, random_transform, unit_cube, zero};
+    use crate::kinematics::{Body, InteractingObjects, NewtonSolver, ObjectRef, PairPotential};
+    use crate::AffineTransform;
+    use hashbrown::HashMap;
+    use nalgebra::{point, Const, Matrix3, OVector, Rotation3, Unit, Vector3};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn rigid(axis: Vector3<f64>, angle: f64, translation: Vector3<f64>) -> AffineTransform<f64> {
+        AffineTransform {
+            translation,
+            transform: Rotation3::from_axis_angle(&Unit::new_normalize(axis), angle).into_inner(),
+        }
+    }
+
+    fn joints() -> [Joint<f64>; 4] {
+        let (anchor_a, anchor_b) = (point![0.5, 0., 0.], point![-0.5, 0., 0.]);
+        let frame = Matrix3::identity();
+        [
+            Joint::ball_socket(anchor_a, anchor_b, 10.),
+            Joint::hinge(anchor_a, anchor_b, Vector3::z(), Vector3::z(), 10.).unwrap(),
+            Joint::fixed(anchor_a, anchor_b, frame, frame, 10.),
+            Joint::slider(anchor_a, anchor_b, frame, frame, &identity(), 10.).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn allowed_motions_keep_joints() {
+        let [ball_socket, hinge, fixed, slider] = joints();
+        //  The second body sits next to the first, and both are turned together.
+        let turn = rigid(Vector3::new(1., 2., 3.), 0.7, Vector3::new(0.1, 0.2, 0.3));
+        let next = AffineTransform {
+            translation: turn.translation + turn.transform * Vector3::x(),
+            transform: turn.transform,
+        };
+        for joint in [&ball_socket, &hinge, &fixed] {
+            assert!(joint.violation(&turn, &next).norm() < 1e-12);
+        }
+        //  Turning the second body about the anchor only breaks the hinge if it is off axis.
+        let swung = |axis: Vector3<f64>| {
+            let swing = rigid(axis, 0.4, Vector3::zeros()).transform;
+            AffineTransform {
+                translation: Vector3::new(0.5, 0., 0.) + swing * Vector3::new(0.5, 0., 0.),
+                transform: swing,
+            }
+        };
+        assert!(
+            ball_socket
+                .violation(&identity(), &swung(Vector3::y()))
+                .norm()
+                < 1e-12
+        );
+        assert!(hinge.violation(&identity(), &swung(Vector3::z())).norm() < 1e-12);
+        assert!(hinge.violation(&identity(), &swung(Vector3::y())).norm() > 0.1);
+        assert!(fixed.violation(&identity(), &swung(Vector3::z())).norm() > 0.1);
+        //  Sliding along the first axis, but not across it.
+        let slid = |offset: Vector3<f64>| AffineTransform {
+            translation: Vector3::x() + offset,
+            transform: Matrix3::identity(),
+        };
+        assert!(slider.violation(&identity(), &slid(Vector3::x())).norm() < 1e-12);
+        assert!(slider.violation(&identity(), &slid(Vector3::y())).norm() > 0.9);
+        assert!(fixed.violation(&identity(), &slid(Vector3::x())).norm() > 0.9);
+    }
+
+    #[test]
+    fn hinge_rejects_zero_axes() {
+        let anchor = point![0., 0., 0.];
+        assert!(Joint::hinge(anchor, anchor, Vector3::z(), Vector3::zeros(), 10.).is_none());
+        assert!(Joint::hinge(anchor, anchor, Vector3::zeros(), Vector3::z(), 10.).is_none());
+    }
+
+    #[test]
+    fn slider_rejects_degenerate_frames() {
+        let anchor = point![0., 0., 0.];
+        let mut frame = Matrix3::identity();
+        frame.set_column(2, &Vector3::zeros());
+        assert!(Joint::slider(anchor, anchor, frame, frame, &identity(), 10.).is_none());
+        //  Flattened across the axis by the state of the first body.
+        let flattened = AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::from_diagonal(&Vector3::new(1., 0., 1.)),
+        };
+        let frame = Matrix3::identity();
+        assert!(Joint::slider(anchor, anchor, frame, frame, &flattened, 10.).is_none());
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let (a, b) = (random_transform(&mut rng), random_transform(&mut rng));
+        for mut joint in joints() {
+            joint.update_multiplier(&random_transform(&mut rng), &random_transform(&mut rng));
+            let (grad_a, grad_b) = joint.gradient(&a, &b);
+            let mut gradient = OVector::<f64, Const<24>>::zeros();
+            gradient
+                .fixed_rows_mut::<12>(0)
+                .copy_from(&OVector::<f64, Const<12>>::from(grad_a));
+            gradient
+                .fixed_rows_mut::<12>(12)
+                .copy_from(&OVector::<f64, Const<12>>::from(grad_b));
+            let hessian = joint.hessian(&a, &b);
+            let epsilon = 1e-6;
+            for i in 0..24 {
+                let shifted = |sign: f64| {
+                    let (mut a, mut b) = (
+                        OVector::<f64, Const<12>>::from(a.clone()),
+                        OVector::<f64, Const<12>>::from(b.clone()),
+                    );
+                    if i < 12 {
+                        a[i] += sign * epsilon;
+                    } else {
+                        b[i - 12] += sign * epsilon;
+                    }
+                    (AffineTransform::from(a), AffineTransform::from(b))
+                };
+                let ((a_plus, b_plus), (a_minus, b_minus)) = (shifted(1.), shifted(-1.));
+                let difference = (joint.potential(&a_plus, &b_plus)
+                    - joint.potential(&a_minus, &b_minus))
+                    / (2. * epsilon);
+                assert!((difference - gradient[i]).abs() < 1e-5 * gradient[i].abs().max(1.));
+                let (plus, minus) = (
+                    joint.gradient(&a_plus, &b_plus),
+                    joint.gradient(&a_minus, &b_minus),
+                );
+                let (plus, minus) = (
+                    OVector::<f64, Const<12>>::from(plus.0 - minus.0),
+                    OVector::<f64, Const<12>>::from(plus.1 - minus.1),
+                );
+                for j in 0..12 {
+                    assert!((plus[j] / (2. * epsilon) - hessian[(j, i)]).abs() < 1e-5);
+                    assert!((minus[j] / (2. * epsilon) - hessian[(j + 12, i)]).abs() < 1e-5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multipliers_remove_drift() {
+        let (moments, zero) = (unit_cube(), zero());
+        let hanging = AffineTransform {
+            translation: Vector3::new(0., -1., 0.),
+            transform: Matrix3::identity(),
+        };
+        let gravity = AffineTransform {
+            translation: Vector3::new(0., -9.8, 0.),
+            transform: Matrix3::zeros(),
+        };
+        let ceiling = identity();
+        let mut joint = Joint::ball_socket(point![0., 0., 0.], point![0., 1., 0.], 1e3);
+        let mut violations = Vec::new();
+        for _ in 0..6 {
+            let report = {
+                let bodies = vec![
+                    Body::Static(&ceiling),
+                    Body::Dynamic(ObjectRef {
+                        state: &hanging,
+                        velocity: &zero,
+                        moments: &moments,
+                        forces: &gravity,
+                        density: 1.,
+                    }),
+                ];
+                let objects = InteractingObjects::from_bodies(
+                    bodies,
+                    HashMap::from_iter([((0, 1), &joint)]),
+                    1e3,
+                );
+                NewtonSolver::default().solve(&objects, 0.1).unwrap()
+            };
+            assert!(report.converged);
+            violations.push(joint.violation(&report.state[0], &report.state[1]).norm());
+            joint.update_multiplier(&report.state[0], &report.state[1]);
+        }
+        //  The penalty alone sags, and the multiplier ends up holding the weight (less what the
+        //  body gives by stretching).
+        assert!(violations[0] > 1e-3);
+        assert!(violations[5] < 1e-5 * violations[0]);
+        assert!(0.95 * 9.8 < joint.multiplier[1] && joint.multiplier[1] < 9.8);
+    }
+}
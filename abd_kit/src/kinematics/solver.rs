@@ -0,0 +1,491 @@
+use crate::kinematics::{Body, InteractingObjects, PairPotential};
+use crate::AffineTransform;
+use faer::prelude::*;
+use faer::{Col, Side};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Projected Newton's method on the incremental potential of a set of [`InteractingObjects`].
+/// Only the dynamic bodies are solved for; the others are moved to their prescribed states.
+/// The line search of every iteration is capped by the largest step that the continuous
+/// collision detection of the close pairs (see [`PairPotential::max_step`]) considers safe, so
+/// that no iterate ever leaves the feasible (intersection free) region.
+#[derive(Clone, Debug)]
+pub struct NewtonSolver<RF> {
+    /// The iterations stop once half the squared Newton decrement, `½ gᵀ H⁻¹ g`, falls below
+    /// this.
+    pub tolerance: RF,
+    /// The maximum number of Newton iterations per time step.
+    pub max_iterations: usize,
+    /// The sufficient decrease constant of the backtracking (Armijo) line search.
+    pub armijo: RF,
+    /// The factor that the step is scaled by each time the line search backtracks.
+    pub backtrack: RF,
+    /// The smallest step fraction the line search will try before giving up.
+    pub min_step: RF,
+}
+
+/// The outcome of a single time step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolverReport<RF> {
+    /// The state of each object at the end of the time step.
+    pub state: Vec<AffineTransform<RF>>,
+    /// The velocity of each object at the end of the time step.
+    pub velocity: Vec<AffineTransform<RF>>,
+    /// The number of Newton iterations taken.
+    pub iterations: usize,
+    /// Whether the Newton decrement fell below the tolerance.
+    pub converged: bool,
+}
+
+/// The error of [`NewtonSolver::solve`] when the kinematic bodies cannot reach their targets
+/// without passing through the dynamic bodies in their way, because those are pinned (against a
+/// static body, say).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blocked {
+    /// The indices of the kinematic bodies that fell short of their targets.
+    pub kinematic: Vec<usize>,
+}
+
+impl std::fmt::Display for Blocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the kinematic bodies {:?} are blocked from reaching their targets",
+            self.kinematic
+        )
+    }
+}
+
+impl std::error::Error for Blocked {}
+
+impl<RF: RealField> Default for NewtonSolver<RF> {
+    fn default() -> Self {
+        Self {
+            tolerance: RF::from_f64(1e-9).unwrap(),
+            max_iterations: 100,
+            armijo: RF::from_f64(1e-4).unwrap(),
+            backtrack: RF::from_f64(0.5).unwrap(),
+            min_step: RF::from_f64(1e-10).unwrap(),
+        }
+    }
+}
+
+impl<RF: RealField> NewtonSolver<RF> {
+    /// Advances `objects` by `time_step`, starting from their current state.
+    ///
+    /// The kinematic bodies start out at their current state too.  They are moved along the
+    /// straight line towards their targets, carrying along the dynamic bodies that they push, as
+    /// far as the continuous collision detection of the close pairs allows.  After that the
+    /// dynamic bodies are solved for, and so on until the targets are reached, so the kinematic
+    /// bodies always end up exactly at their targets.  If they are still short of them after
+    /// [`Self::max_iterations`], they are [`Blocked`].
+    pub fn solve<C: PairPotential<RF>>(
+        &self,
+        objects: &InteractingObjects<RF, C>,
+        time_step: RF,
+    ) -> Result<SolverReport<RF>, Blocked> {
+        let mut param = Vec::from_iter(objects.objects.iter().map(|o| o.state().clone()));
+        let kinematic = objects
+            .objects
+            .iter()
+            .any(|o| matches!(o, Body::Kinematic { .. }));
+        let mut iterations = 0;
+        let converged = loop {
+            let arrived =
+                !kinematic || self.advance_kinematic(objects, &mut param, time_step.clone());
+            let converged = self.minimize(objects, &mut param, time_step.clone(), &mut iterations);
+            if arrived {
+                break converged;
+            }
+            if iterations >= self.max_iterations {
+                return Err(Blocked {
+                    kinematic: Vec::from_iter(
+                        (0..objects.objects.len())
+                            .filter(|&i| matches!(objects.objects[i], Body::Kinematic { .. })),
+                    ),
+                });
+            }
+        };
+        let inv_h = time_step.recip();
+        let velocity = Vec::from_iter(
+            objects
+                .objects
+                .iter()
+                .zip(&param)
+                .map(|(o, q)| (q.clone() - o.state().clone()).scaled_by(inv_h.clone())),
+        );
+        Ok(SolverReport {
+            state: param,
+            velocity,
+            iterations,
+            converged,
+        })
+    }
+
+    /// Moves the kinematic bodies in `param` towards their targets, along with the first order
+    /// response of the dynamic bodies that they are coupled to, as far as the close pairs allow.
+    /// Returns whether the kinematic bodies all reached their targets.
+    fn advance_kinematic<C: PairPotential<RF>>(
+        &self,
+        objects: &InteractingObjects<RF, C>,
+        param: &mut [AffineTransform<RF>],
+        time_step: RF,
+    ) -> bool {
+        let h2 = time_step.clone() * time_step.clone();
+        let is_kinematic = |i: usize| matches!(objects.objects[i], Body::Kinematic { .. });
+        let zero = AffineTransform::from_matrix(&Matrix3x4::zeros());
+        let moved = Vec::from_iter(objects.objects.iter().zip(&*param).map(|(o, q)| match o {
+            Body::Kinematic { target, .. } => target.clone() - q.clone(),
+            _ => zero.clone(),
+        }));
+        //  A Newton step of the dynamic bodies with the kinematic ones moved, `H_ff p = -(g + H_fk
+        //  moved)`.  The rows of the others are those of the identity, so they come out as is.
+        let mut rhs = objects.free_gradient(param, time_step.clone());
+        for (&(a, b), pair) in &objects.close {
+            let (free, fixed, offset) = match (is_kinematic(a), is_kinematic(b)) {
+                (false, true) => (a, b, (0, 12)),
+                (true, false) => (b, a, (12, 0)),
+                _ => continue,
+            };
+            if objects.objects[free].is_dynamic() {
+                let hessian = pair.hessian(&param[a], &param[b]).scale(h2.clone());
+                let coupling = hessian.fixed_view::<12, 12>(offset.0, offset.1)
+                    * OVector::<RF, Const<12>>::from(moved[fixed].clone());
+                rhs[free] += AffineTransform::from(coupling);
+            }
+        }
+        for ((o, r), m) in objects.objects.iter().zip(&mut rhs).zip(&moved) {
+            *r = match o {
+                Body::Dynamic(_) => r.scaled_by(-RF::one()),
+                _ => m.clone(),
+            };
+        }
+        let rhs = flatten(&rhs);
+        let hessian = objects.projected_hessian(param, time_step);
+        let direction = match hessian.sp_cholesky(Side::Lower) {
+            Ok(llt) => unflatten(&llt.solve(&rhs)),
+            Err(_) => moved,
+        };
+        let step = objects
+            .close
+            .iter()
+            .map(|(&(a, b), pair)| {
+                pair.max_step(&param[a], &direction[a], &param[b], &direction[b])
+            })
+            .fold(RF::one(), |acc, s| acc.min(s));
+        let arrived = step >= RF::one();
+        for ((o, q), p) in objects.objects.iter().zip(param.iter_mut()).zip(&direction) {
+            *q = match o {
+                Body::Kinematic { target, .. } if arrived => target.clone(),
+                Body::Static(_) => continue,
+                _ => q.clone() + p.scaled_by(step.clone()),
+            };
+        }
+        arrived
+    }
+
+    /// Runs projected Newton on the dynamic bodies in `param`, with the others held in place,
+    /// counting the iterations in `iterations`.  Returns whether the Newton decrement fell below
+    /// the tolerance.
+    fn minimize<C: PairPotential<RF>>(
+        &self,
+        objects: &InteractingObjects<RF, C>,
+        param: &mut Vec<AffineTransform<RF>>,
+        time_step: RF,
+        iterations: &mut usize,
+    ) -> bool {
+        let half = (RF::one() + RF::one()).recip();
+        let mut energy = objects.potential(param, time_step.clone());
+        while *iterations < self.max_iterations {
+            let gradient = flatten(&objects.free_gradient(param, time_step.clone()));
+            let hessian = objects.projected_hessian(param, time_step.clone());
+            let descent = -&gradient;
+            let direction = match hessian.sp_cholesky(Side::Lower) {
+                Ok(llt) => llt.solve(&descent),
+                Err(_) => descent,
+            };
+            let slope = gradient.transpose() * &direction;
+            *iterations += 1;
+            if -slope.clone() * half.clone() < self.tolerance {
+                return true;
+            }
+            let direction = unflatten(&direction);
+            let mut step = objects
+                .close
+                .iter()
+                .map(|(&(a, b), pair)| {
+                    pair.max_step(&param[a], &direction[a], &param[b], &direction[b])
+                })
+                .fold(RF::one(), |acc, s| acc.min(s));
+            let (next, next_energy) = loop {
+                let next = Vec::from_iter(
+                    param
+                        .iter()
+                        .zip(&direction)
+                        .map(|(q, p)| q.clone() + p.scaled_by(step.clone())),
+                );
+                let next_energy = objects.potential(&next, time_step.clone());
+                let decrease = self.armijo.clone() * step.clone() * slope.clone();
+                if next_energy <= energy.clone() + decrease || step < self.min_step {
+                    break (next, next_energy);
+                }
+                step *= self.backtrack.clone();
+            };
+            if step < self.min_step {
+                break;
+            }
+            *param = next;
+            energy = next_energy;
+        }
+        false
+    }
+
+    /// Advances each of the `isles` (see [`InteractingObjects::create_isles`]) one after the
+    /// other.  The reports are in the same order as the isles, and the indices of a [`Blocked`]
+    /// isle are those within the isle.
+    pub fn solve_isles<C: PairPotential<RF>>(
+        &self,
+        isles: &[InteractingObjects<RF, C>],
+        time_step: RF,
+    ) -> Vec<Result<SolverReport<RF>, Blocked>> {
+        Vec::from_iter(isles.iter().map(|isle| self.solve(isle, time_step.clone())))
+    }
+
+    /// The same as [`Self::solve_isles`], but the isles are advanced in parallel.  The results
+    /// are identical to the serial version.
+    #[cfg(feature = "rayon")]
+    pub fn par_solve_isles<C: PairPotential<RF> + Sync>(
+        &self,
+        isles: &[InteractingObjects<RF, C>],
+        time_step: RF,
+    ) -> Vec<Result<SolverReport<RF>, Blocked>>
+    where
+        RF: Send + Sync,
+    {
+        isles
+            .par_iter()
+            .map(|isle| self.solve(isle, time_step.clone()))
+            .collect()
+    }
+}
+
+/// Concatenates the 12-vectors of the transforms.
+fn flatten<RF: RealField>(param: &[AffineTransform<RF>]) -> Col<RF> {
+    let mut flat = Col::zeros(12 * param.len());
+    for (n, q) in param.iter().enumerate() {
+        for (i, x) in OVector::<RF, Const<12>>::from(q.clone()).iter().enumerate() {
+            flat[12 * n + i] = x.clone();
+        }
+    }
+    flat
+}
+
+/// The inverse of [`flatten`].
+fn unflatten<RF: RealField>(flat: &Col<RF>) -> Vec<AffineTransform<RF>> {
+    Vec::from_iter((0..flat.nrows() / 12).map(|n| {
+        AffineTransform::from(OVector::<RF, Const<12>>::from_fn(|i, _| {
+            flat[12 * n + i].clone()
+        }))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kinematics::contact::{Contact, ContactPrimitives};
+    use crate::kinematics::tests::{at_rest, identity, random_transform, unit_cube, zero, Spring};
+    use crate::kinematics::{Body, InteractingObjects, NewtonSolver, ObjectRef};
+    use crate::AffineTransform;
+    use hashbrown::HashMap;
+    use nalgebra::{point, Matrix3, Vector3};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn free_fall_reaches_prediction() {
+        let (state, moments) = (identity(), unit_cube());
+        let velocity = AffineTransform {
+            translation: Vector3::new(0.5, 0., 1.),
+            transform: Matrix3::zeros(),
+        };
+        let forces = AffineTransform {
+            translation: Vector3::new(0., 0., -9.8),
+            transform: Matrix3::zeros(),
+        };
+        let object = ObjectRef {
+            state: &state,
+            velocity: &velocity,
+            moments: &moments,
+            forces: &forces,
+            density: 1.,
+        };
+        let predicted = object.predicted_state(0.01);
+        let objects: InteractingObjects<_, Spring> =
+            InteractingObjects::new(vec![object], HashMap::new(), 1e4);
+        let report = NewtonSolver::default().solve(&objects, 0.01).unwrap();
+        assert!(report.converged);
+        assert!(report.iterations <= 3);
+        assert!((report.state[0].to_matrix() - predicted.to_matrix()).norm() < 1e-9);
+    }
+
+    #[test]
+    fn converges_with_pair_potentials() {
+        let mut rng = StdRng::seed_from_u64(987);
+        let moments = unit_cube();
+        let states = [random_transform(&mut rng), random_transform(&mut rng)];
+        let zero = zero();
+        let objects = Vec::from_iter(states.iter().map(|state| at_rest(state, &moments, &zero)));
+        let spring = Spring(1e3);
+        let objects: InteractingObjects<_, Spring> =
+            InteractingObjects::new(objects, HashMap::from_iter([((0, 1), &spring)]), 1e2);
+        let report = NewtonSolver::default().solve(&objects, 0.05).unwrap();
+        assert!(report.converged);
+        let gradient = objects.gradient(&report.state, 0.05);
+        assert!(gradient.iter().all(|g| g.to_matrix().norm() < 1e-6));
+    }
+
+    #[test]
+    fn prescribed_bodies_stay_put() {
+        let mut rng = StdRng::seed_from_u64(31);
+        let moments = unit_cube();
+        let states = [
+            random_transform(&mut rng),
+            random_transform(&mut rng),
+            random_transform(&mut rng),
+        ];
+        let target = random_transform(&mut rng);
+        let zero = zero();
+        let bodies = vec![
+            Body::Dynamic(at_rest(&states[0], &moments, &zero)),
+            Body::Static(&states[1]),
+            Body::Kinematic {
+                state: &states[2],
+                target: target.clone(),
+            },
+        ];
+        let spring = Spring(1e3);
+        let objects: InteractingObjects<_, Spring> = InteractingObjects::from_bodies(
+            bodies,
+            HashMap::from_iter([((0, 1), &spring), ((2, 0), &spring)]),
+            1e2,
+        );
+        let report = NewtonSolver::default().solve(&objects, 0.05).unwrap();
+        assert!(report.converged);
+        assert_eq!(report.state[1], states[1]);
+        assert_eq!(report.state[2], target);
+        assert_eq!(report.velocity[1].to_matrix().norm(), 0.);
+        let gradient = objects.gradient(&report.state, 0.05);
+        assert!(gradient[0].to_matrix().norm() < 1e-6);
+        //  The springs only act on the translations, which balance with the inertia.
+        let stiffness = 1e3 * 0.05 * 0.05;
+        let balance = (states[0].translation
+            + (states[1].translation + target.translation) * stiffness)
+            / (1. + 2. * stiffness);
+        assert!((report.state[0].translation - balance).norm() < 1e-6);
+    }
+
+    fn translated(z: f64) -> AffineTransform<f64> {
+        AffineTransform {
+            translation: Vector3::new(0., 0., z),
+            transform: Matrix3::identity(),
+        }
+    }
+
+    /// The contact of the bottom of a unit box, a little inset, resting on the top of another.
+    fn stacked() -> Contact<f64> {
+        let mut contact = Contact::new(0.1, 1e3);
+        let top =
+            [[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]].map(|[x, y]| point![x, y, 0.5]);
+        for [x, y] in [[-0.4, -0.4], [0.4, -0.4], [0.4, 0.4], [-0.4, 0.4]] {
+            for face in [[top[0], top[1], top[2]], [top[0], top[2], top[3]]] {
+                contact
+                    .primitives
+                    .push(ContactPrimitives::VertexFace(point![x, y, -0.5], face));
+            }
+        }
+        contact
+    }
+
+    #[test]
+    fn kinematic_bodies_push_dynamic_bodies() {
+        let moments = unit_cube();
+        let zero = zero();
+        //  A box pressed down into a resting box of the same size, ending up well inside of it.
+        let (resting, state, target) = (identity(), translated(1.2), translated(0.6));
+        let bodies = vec![
+            Body::Dynamic(at_rest(&resting, &moments, &zero)),
+            Body::Kinematic {
+                state: &state,
+                target: target.clone(),
+            },
+        ];
+        let contact = stacked();
+        let objects: InteractingObjects<_, Contact<f64>> =
+            InteractingObjects::from_bodies(bodies, HashMap::from_iter([((1, 0), &contact)]), 1e2);
+        let report = NewtonSolver::default().solve(&objects, 0.1).unwrap();
+        assert!(report.converged);
+        assert_eq!(report.state[1], target);
+        //  Pushed out of the way rather than passed through.
+        let gap = (report.state[1].translation.z - 0.5) - (report.state[0].translation.z + 0.5);
+        assert!(0. < gap && gap < 0.1, "{gap}");
+    }
+
+    #[test]
+    fn pinned_bodies_block_kinematic_bodies() {
+        let moments = unit_cube();
+        let zero = zero();
+        //  The same, but with the resting box on a floor, and pressed down past the top of it.
+        let (resting, state, target, floor) =
+            (identity(), translated(1.2), translated(-0.2), translated(-1.05));
+        let bodies = vec![
+            Body::Dynamic(at_rest(&resting, &moments, &zero)),
+            Body::Kinematic {
+                state: &state,
+                target,
+            },
+            Body::Static(&floor),
+        ];
+        let contact = stacked();
+        let objects: InteractingObjects<_, Contact<f64>> = InteractingObjects::from_bodies(
+            bodies,
+            HashMap::from_iter([((1, 0), &contact), ((0, 2), &contact)]),
+            //  Stiff enough that the resting box can not be flattened out of the way.
+            1e6,
+        );
+        let blocked = NewtonSolver::default().solve(&objects, 0.1).unwrap_err();
+        assert_eq!(blocked.kinematic, vec![1]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_isles_match_serial() {
+        use crate::kinematics::tests::Fixed;
+        let mut rng = StdRng::seed_from_u64(55);
+        let moments = unit_cube();
+        let states = Vec::from_iter((0..16).map(|_| random_transform(&mut rng)));
+        let velocities = Vec::from_iter((0..16).map(|_| random_transform(&mut rng)));
+        let zero = zero();
+        let objects = Vec::from_iter(states.iter().zip(&velocities).map(|(state, velocity)| {
+            ObjectRef {
+                state,
+                velocity,
+                moments: &moments,
+                forces: &zero,
+                density: 1.,
+            }
+        }));
+        let spring = Spring(1e2);
+        let objects: InteractingObjects<_, Spring> = InteractingObjects::new(
+            objects,
+            HashMap::from_iter((0..8).map(|i| ((2 * i, 2 * i + 1), &spring))),
+            1e2,
+        );
+        let isles = objects.create_isles(&Fixed(vec![], vec![]), (), |part| part.0);
+        assert_eq!(isles.len(), 8);
+        let solver = NewtonSolver::default();
+        assert_eq!(
+            solver.solve_isles(&isles, 0.02),
+            solver.par_solve_isles(&isles, 0.02)
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Automatic selection of the barrier stiffness `κ`, following the IPC paper.
+
+use crate::kinematics::contact::{barrier_second_derivative, Contact};
+use crate::kinematics::{Body, InteractingObjects, PairPotential};
+use crate::AffineTransform;
+
+/// A pair potential whose barrier stiffness can be chosen by [`AdaptiveStiffness`].
+pub trait Barrier<RF: RealField>: PairPotential<RF> {
+    /// The stiffness `κ` of the barrier.
+    fn stiffness(&self) -> RF;
+    /// Replaces the stiffness of the barrier.
+    fn set_stiffness(&mut self, stiffness: RF);
+    /// The gradient of the barrier with unit stiffness.
+    fn barrier_gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>);
+    /// The smallest distance between the objects, or `None` if nothing of them is being tracked.
+    fn min_distance(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> Option<RF>;
+}
+
+impl<RF: RealField> Barrier<RF> for Contact<RF> {
+    fn stiffness(&self) -> RF {
+        self.stiffness.clone()
+    }
+
+    fn set_stiffness(&mut self, stiffness: RF) {
+        self.stiffness = stiffness;
+    }
+
+    fn barrier_gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>) {
+        Contact::barrier_gradient(self, a, b)
+    }
+
+    fn min_distance(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> Option<RF> {
+        Contact::min_distance(self, a, b)
+    }
+}
+
+/// A barrier paired with another potential, such as its friction.  Only the barrier is affected.
+impl<RF: RealField, B: Barrier<RF>, P: PairPotential<RF>> Barrier<RF> for (B, P) {
+    fn stiffness(&self) -> RF {
+        self.0.stiffness()
+    }
+
+    fn set_stiffness(&mut self, stiffness: RF) {
+        self.0.set_stiffness(stiffness);
+    }
+
+    fn barrier_gradient(
+        &self,
+        a: &AffineTransform<RF>,
+        b: &AffineTransform<RF>,
+    ) -> (AffineTransform<RF>, AffineTransform<RF>) {
+        self.0.barrier_gradient(a, b)
+    }
+
+    fn min_distance(&self, a: &AffineTransform<RF>, b: &AffineTransform<RF>) -> Option<RF> {
+        self.0.min_distance(a, b)
+    }
+}
+
+/// Chooses the barrier stiffness `κ` of the close pairs of an [`InteractingObjects`].
+///
+/// [`Self::new`] picks the initial `κ` that best balances the barrier gradient against the rest
+/// of the incremental potential, bounded below by a value scaled to the mass of the objects and
+/// the size of the scene.  After every time step [`Self::update`] grows `κ` if the contacts keep
+/// getting closer than [`Self::fraction`] of `d̂`.  The chosen `κ` has to be copied to the
+/// barriers with [`Barrier::set_stiffness`].
+#[derive(Clone, Debug)]
+pub struct AdaptiveStiffness<RF> {
+    /// The fraction of the barrier thickness below which contacts are considered to be too
+    /// close.  Defaults to `1e-2`.
+    pub fraction: RF,
+    /// The factor that `κ` is multiplied by when the contacts are too close.  Defaults to `2`.
+    pub growth: RF,
+    barrier_thickness: RF,
+    stiffness: RF,
+    min: RF,
+    max: RF,
+    previous_distance: Option<RF>,
+}
+
+impl<RF: RealField> AdaptiveStiffness<RF> {
+    /// The initial stiffness for `objects` at `param`, with barriers of thickness
+    /// `barrier_thickness` in a scene whose size (the diagonal of its bounding box, for example)
+    /// is `scene_scale`.
+    pub fn new<C: Barrier<RF>>(
+        objects: &InteractingObjects<RF, C>,
+        param: &[AffineTransform<RF>],
+        time_step: RF,
+        barrier_thickness: RF,
+        scene_scale: RF,
+    ) -> Self {
+        debug_assert_eq!(param.len(), objects.len());
+        let h2 = time_step.clone() * time_step.clone();
+        let masses = Vec::from_iter(objects.objects.iter().filter_map(|body| match body {
+            Body::Dynamic(object) => {
+                Some(object.moments.compact_m(object.density.clone())[(0, 0)].clone())
+            }
+            _ => None,
+        }));
+        let mass = masses.iter().fold(RF::zero(), |acc, m| acc + m.clone())
+            / RF::from_usize(masses.len().max(1)).unwrap();
+        //  The barrier Hessian at a distance that is tiny compared to the scene should be
+        //  comparable to the (scaled) mass.  In a large scene with a thin barrier that distance
+        //  can be past the barrier, where its Hessian is zero, so half the barrier thickness is
+        //  used instead.
+        let d = RF::from_f64(1e-8).unwrap() * scene_scale;
+        let dhat2 = barrier_thickness.clone() * barrier_thickness.clone();
+        let mut d2 = d.clone() * d;
+        if d2 >= dhat2 {
+            d2 = dhat2.clone() / RF::from_u32(4).unwrap();
+        }
+        let min = RF::from_f64(1e11).unwrap() * mass
+            / (RF::from_u32(4).unwrap()
+                * d2.clone()
+                * barrier_second_derivative(d2, dhat2)
+                * h2.clone());
+        let max = min.clone() * RF::from_u32(100).unwrap();
+        //  The stiffness that minimizes `|∇E + κ h² ∇B|`.
+        let mut barrier = vec![AffineTransform::from_matrix(&Matrix3x4::zeros()); objects.len()];
+        for (&(a, b), pair) in &objects.close {
+            let (grad_a, grad_b) = pair.barrier_gradient(&param[a], &param[b]);
+            barrier[a] += grad_a.scaled_by(h2.clone());
+            barrier[b] += grad_b.scaled_by(h2.clone());
+        }
+        let rest = objects.object_gradient(param, time_step);
+        let (dot, norm2) =
+            rest.iter()
+                .zip(&barrier)
+                .fold((RF::zero(), RF::zero()), |(dot, norm2), (g, b)| {
+                    let (g, b) = (g.to_matrix(), b.to_matrix());
+                    (dot + g.dot(&b), norm2 + b.norm_squared())
+                });
+        let stiffness = if norm2.is_zero() {
+            min.clone()
+        } else {
+            (-dot / norm2).max(min.clone()).min(max.clone())
+        };
+        Self {
+            fraction: RF::from_f64(1e-2).unwrap(),
+            growth: RF::one() + RF::one(),
+            barrier_thickness,
+            stiffness,
+            min,
+            max,
+            previous_distance: None,
+        }
+    }
+
+    /// The current stiffness `κ`.
+    pub fn stiffness(&self) -> RF {
+        self.stiffness.clone()
+    }
+
+    /// The lower bound on `κ` derived from the mass of the objects and the size of the scene.
+    pub fn min(&self) -> RF {
+        self.min.clone()
+    }
+
+    /// The upper bound on `κ`, which [`Self::update`] never grows past.
+    pub fn max(&self) -> RF {
+        self.max.clone()
+    }
+
+    /// Updates `κ` with the state `param` at the end of a time step, and returns it so that it
+    /// can be set on the barriers and logged.  `κ` is grown when the closest contact was closer
+    /// than [`Self::fraction`] of `d̂` at the end of both this and the previous step, and has
+    /// gotten closer still.
+    pub fn update<C: Barrier<RF>>(
+        &mut self,
+        objects: &InteractingObjects<RF, C>,
+        param: &[AffineTransform<RF>],
+    ) -> RF {
+        debug_assert_eq!(param.len(), objects.len());
+        let distance = objects
+            .close
+            .iter()
+            .filter_map(|(&(a, b), pair)| pair.min_distance(&param[a], &param[b]))
+            .reduce(|acc, d| acc.min(d));
+        let threshold = self.fraction.clone() * self.barrier_thickness.clone();
+        if let (Some(previous), Some(current)) = (&self.previous_distance, &distance) {
+            if *previous < threshold && *current < threshold && current < previous {
+                self.stiffness =
+                    (self.stiffness.clone() * self.growth.clone()).min(self.max.clone());
+            }
+        }
+        self.previous_distance = distance;
+        self.stiffness.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kinematics::contact::{Contact, ContactPrimitives};
+    use crate::kinematics::tests::{at_rest, identity, unit_cube, zero};
+    use crate::kinematics::{AdaptiveStiffness, Barrier, InteractingObjects, ObjectRef};
+    use crate::AffineTransform;
+    use hashbrown::HashMap;
+    use nalgebra::{point, Matrix3, Vector3};
+
+    fn translated(z: f64) -> AffineTransform<f64> {
+        AffineTransform {
+            translation: Vector3::new(0., 0., z),
+            transform: Matrix3::identity(),
+        }
+    }
+
+    /// A vertex of the first object right above a triangle of the second.
+    fn vertex_face(barrier_thickness: f64) -> Contact<f64> {
+        let mut contact = Contact::new(barrier_thickness, 1.);
+        contact.primitives.push(ContactPrimitives::VertexFace(
+            point![0., 0., 0.],
+            [
+                point![-1., -1., 0.],
+                point![1., -1., 0.],
+                point![0., 1., 0.],
+            ],
+        ));
+        contact
+    }
+
+    #[test]
+    fn initial_stiffness_balances_gradient() {
+        let (moments, zero) = (unit_cube(), zero());
+        let states = [translated(0.05), identity()];
+        let velocity = AffineTransform {
+            translation: Vector3::new(0., 0., -1.),
+            transform: Matrix3::zeros(),
+        };
+        let velocities = [velocity, zero.clone()];
+        let objects = Vec::from_iter((0..2).map(|i| ObjectRef {
+            state: &states[i],
+            velocity: &velocities[i],
+            moments: &moments,
+            forces: &zero,
+            density: 1.,
+        }));
+        let contact = vertex_face(0.1);
+        let objects =
+            InteractingObjects::new(objects, HashMap::from_iter([((0, 1), &contact)]), 1.);
+        let param = [translated(0.045), identity()];
+        let time_step = 0.01;
+        let stiffness = AdaptiveStiffness::new(&objects, &param, time_step, 0.1, 1.);
+        let kappa = stiffness.stiffness();
+        assert!(stiffness.min() < kappa && kappa < stiffness.max());
+        let residual = |kappa: f64| {
+            let mut contact = contact.clone();
+            contact.set_stiffness(kappa);
+            let objects = InteractingObjects::from_bodies(
+                objects.objects.clone(),
+                HashMap::from_iter([((0, 1), &contact)]),
+                1.,
+            );
+            objects
+                .gradient(&param, time_step)
+                .iter()
+                .map(|g| g.to_matrix().norm_squared())
+                .sum::<f64>()
+        };
+        assert!(residual(kappa) < residual(kappa * 1.01));
+        assert!(residual(kappa) < residual(kappa * 0.99));
+    }
+
+    #[test]
+    fn grows_while_contacts_get_closer() {
+        let (state, moments, zero) = (identity(), unit_cube(), zero());
+        let objects = Vec::from_iter((0..2).map(|_| at_rest(&state, &moments, &zero)));
+        let contact = vertex_face(0.1);
+        let objects =
+            InteractingObjects::new(objects, HashMap::from_iter([((0, 1), &contact)]), 1.);
+        let mut stiffness =
+            AdaptiveStiffness::new(&objects, &[translated(1.), identity()], 0.01, 0.1, 1.);
+        let initial = stiffness.stiffness();
+        assert_eq!(initial, stiffness.min());
+        //  Too close, but there is nothing to compare to yet.
+        assert_eq!(
+            stiffness.update(&objects, &[translated(5e-4), identity()]),
+            initial
+        );
+        //  Moving away does not change anything.
+        assert_eq!(
+            stiffness.update(&objects, &[translated(8e-4), identity()]),
+            initial
+        );
+        assert_eq!(
+            stiffness.update(&objects, &[translated(4e-4), identity()]),
+            2. * initial
+        );
+        assert_eq!(
+            stiffness.update(&objects, &[translated(2e-2), identity()]),
+            2. * initial
+        );
+        let mut kappa = 2. * initial;
+        for z in [1e-4, 5e-5, 2e-5, 1e-5, 5e-6, 2e-6, 1e-6, 5e-7] {
+            kappa = stiffness.update(&objects, &[translated(z), identity()]);
+        }
+        assert_eq!(kappa, stiffness.max());
+    }
+
+    #[test]
+    fn finite_in_large_scenes() {
+        let (state, moments, zero) = (identity(), unit_cube(), zero());
+        let objects = Vec::from_iter((0..2).map(|_| at_rest(&state, &moments, &zero)));
+        let contact = vertex_face(1e-3);
+        let objects =
+            InteractingObjects::new(objects, HashMap::from_iter([((0, 1), &contact)]), 1.);
+        //  A kilometer wide scene with a millimeter thick barrier.
+        let stiffness =
+            AdaptiveStiffness::new(&objects, &[translated(1.), identity()], 0.01, 1e-3, 1e6);
+        let kappa = stiffness.stiffness();
+        assert!(kappa.is_finite() && kappa > 0.);
+        assert!(stiffness.max().is_finite());
+    }
+}
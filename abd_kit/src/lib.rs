@@ -9,6 +9,9 @@
 //! The actual physics is implemented using
 //! [Affine Body Dynamics](https://arxiv.org/abs/2201.10022),
 //! which is an intersection free method (when used with contact free collision detection).
+//!
+//! For those who would rather not, the `world` feature adds a minimal [`World`](world::World)
+//! that puts the pieces together.
 
 pub mod collision_detection;
 mod configuration;
@@ -16,6 +19,8 @@ mod moments;
 pub mod spatial;
 pub mod util;
 pub mod kinematics;
+#[cfg(feature = "world")]
+pub mod world;
 
 pub use configuration::*;
 pub use moments::*;
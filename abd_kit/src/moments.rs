@@ -1,3 +1,7 @@
+use crate::AffineTransform;
+use hashbrown::HashSet;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
 /// The mass matrix takes the form of
 /// ```py
 /// Matrix([
@@ -32,6 +36,38 @@ pub struct Moments<T> {
     pub zz: T,
 }
 
+/// Why a triangle mesh does not bound a solid, as reported by [`Moments::from_mesh`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MeshError {
+    /// The edge from the first vertex to the second has no face on its other side, so the mesh
+    /// has a hole.
+    Open([usize; 2]),
+    /// The edge from the first vertex to the second is used in the same direction by more than
+    /// one face, so the faces around it are not consistently oriented (or more than two faces
+    /// meet there).
+    Inconsistent([usize; 2]),
+    /// The faces enclose a negative volume, because they are oriented inwards, or no volume at
+    /// all.
+    Inverted,
+}
+
+impl std::fmt::Display for MeshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshError::Open([a, b]) => write!(f, "the mesh is open at the edge {a}-{b}"),
+            MeshError::Inconsistent([a, b]) => {
+                write!(
+                    f,
+                    "the faces around the edge {a}-{b} are not consistently oriented"
+                )
+            }
+            MeshError::Inverted => write!(f, "the mesh is inside out or encloses no volume"),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
 /// Stores the coefficients of the compacted inverse mass matrix (before the Kronecker product with I_3)
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CompInvMoments<T> {
@@ -48,7 +84,378 @@ pub struct CompInvMoments<T> {
 }
 
 impl<T: RealField> Moments<T> {
+    /// The moments of nothing at all.
+    fn zero() -> Self {
+        let zero = T::zero();
+        Moments {
+            v: zero.clone(),
+            x: zero.clone(),
+            y: zero.clone(),
+            z: zero.clone(),
+            xx: zero.clone(),
+            xy: zero.clone(),
+            xz: zero.clone(),
+            yy: zero.clone(),
+            yz: zero.clone(),
+            zz: zero,
+        }
+    }
+
+    /// The moments of the solid bounded by a closed triangle mesh, whose faces are given by the
+    /// indices of their vertices, in counterclockwise order when seen from the outside.
+    ///
+    /// The integrals are exact (up to rounding): by the divergence theorem they are the sum over
+    /// the faces of the signed integrals over the tetrahedra that join each face to the origin.
+    pub fn from_mesh(vertices: &[Point3<T>], faces: &[[usize; 3]]) -> Result<Self, MeshError> {
+        //  Every directed edge must be matched by exactly one in the opposite direction.
+        let mut edges = HashSet::with_capacity(3 * faces.len());
+        for &[a, b, c] in faces {
+            for edge in [[a, b], [b, c], [c, a]] {
+                if !edges.insert(edge) {
+                    return Err(MeshError::Inconsistent(edge));
+                }
+            }
+        }
+        if let Some(&[a, b]) = edges.iter().find(|&&[a, b]| !edges.contains(&[b, a])) {
+            return Err(MeshError::Open([a, b]));
+        }
+        let mut moments = Self::zero();
+        for &[a, b, c] in faces {
+            let (a, b, c) = (
+                &vertices[a].coords,
+                &vertices[b].coords,
+                &vertices[c].coords,
+            );
+            //  Six times the signed volume of the tetrahedron (0, a, b, c).
+            let det = a.dot(&b.cross(c));
+            let sum = a + b + c;
+            //  ∫ xᵢ xⱼ over a tetrahedron of volume V is V / 20 (Σₖ pₖᵢ pₖⱼ + sᵢ sⱼ), where the
+            //  pₖ are its corners and s their sum.
+            let second = |i: usize, j: usize| {
+                a[i].clone() * a[j].clone()
+                    + b[i].clone() * b[j].clone()
+                    + c[i].clone() * c[j].clone()
+                    + sum[i].clone() * sum[j].clone()
+            };
+            moments.v += det.clone();
+            moments.x += det.clone() * sum.x.clone();
+            moments.y += det.clone() * sum.y.clone();
+            moments.z += det.clone() * sum.z.clone();
+            moments.xx += det.clone() * second(0, 0);
+            moments.xy += det.clone() * second(0, 1);
+            moments.xz += det.clone() * second(0, 2);
+            moments.yy += det.clone() * second(1, 1);
+            moments.yz += det.clone() * second(1, 2);
+            moments.zz += det * second(2, 2);
+        }
+        if moments.v <= T::zero() {
+            return Err(MeshError::Inverted);
+        }
+        let (six, twenty_four, one_twenty) = (
+            T::from_u32(6).unwrap(),
+            T::from_u32(24).unwrap(),
+            T::from_u32(120).unwrap(),
+        );
+        moments.v /= six;
+        moments.x /= twenty_four.clone();
+        moments.y /= twenty_four.clone();
+        moments.z /= twenty_four;
+        moments.xx /= one_twenty.clone();
+        moments.xy /= one_twenty.clone();
+        moments.xz /= one_twenty.clone();
+        moments.yy /= one_twenty.clone();
+        moments.yz /= one_twenty.clone();
+        moments.zz /= one_twenty;
+        Ok(moments)
+    }
+
+    /// The moments of a thin shell of uniform `thickness` around a triangle surface, which need
+    /// not be closed or consistently oriented.  Each triangle is taken as a slab of the given
+    /// thickness centered on it, so the moments are area weighted with the volume `area ×
+    /// thickness`, plus the second moments `area × thickness³ / 12` across the triangle.  The
+    /// latter matter: without them the mass matrix of a flat shell would be singular, as its
+    /// affine transform along the normal would carry no inertia.
+    pub fn from_surface(vertices: &[Point3<T>], faces: &[[usize; 3]], thickness: T) -> Self {
+        let two = T::one() + T::one();
+        let (three, twelve) = (T::from_u32(3).unwrap(), T::from_u32(12).unwrap());
+        let across = thickness.clone() * thickness.clone() / twelve.clone();
+        let mut moments = Self::zero();
+        for &[a, b, c] in faces {
+            let (a, b, c) = (
+                &vertices[a].coords,
+                &vertices[b].coords,
+                &vertices[c].coords,
+            );
+            let normal = (b - a).cross(&(c - a));
+            let area = normal.norm() / two.clone();
+            if area.is_zero() {
+                continue;
+            }
+            let normal = normal.scale((area.clone() * two.clone()).recip());
+            let sum = a + b + c;
+            //  ∫ x xᵀ over a triangle of area A is A / 12 (Σₖ pₖ pₖᵀ + s sᵀ), where the pₖ are
+            //  its corners and s their sum.
+            let second = (a * a.transpose()
+                + b * b.transpose()
+                + c * c.transpose()
+                + &sum * sum.transpose())
+            .scale(area.clone() / twelve.clone())
+                + (&normal * normal.transpose()).scale(area.clone() * across.clone());
+            let first = sum.scale(area.clone() / three.clone());
+            moments += Self::from_parts(area, first, second).scaled(thickness.clone());
+        }
+        moments
+    }
+
+    /// The moments of a thin wire along a polyline, with a round cross section of the given
+    /// area.  Like [`Self::from_surface`], each segment is taken as a solid cylinder, whose second
+    /// moments across the segment, `length × area² / 4π`, keep the mass matrix invertible even
+    /// for a straight wire.
+    pub fn from_polyline(points: &[Point3<T>], cross_section: T) -> Self {
+        let (two, six) = (T::one() + T::one(), T::from_u32(6).unwrap());
+        let across = cross_section.clone() / (T::two_pi() + T::two_pi());
+        let mut moments = Self::zero();
+        for segment in points.windows(2) {
+            let (a, b) = (&segment[0].coords, &segment[1].coords);
+            let direction = b - a;
+            let length = direction.norm();
+            if length.is_zero() {
+                continue;
+            }
+            let direction = direction.scale(length.clone().recip());
+            let sum = a + b;
+            //  ∫ x xᵀ over a segment of length L is L / 6 (a aᵀ + b bᵀ + s sᵀ).
+            let second = (a * a.transpose() + b * b.transpose() + &sum * sum.transpose())
+                .scale(length.clone() / six.clone())
+                + (Matrix3::identity() - &direction * direction.transpose())
+                    .scale(length.clone() * across.clone());
+            let first = sum.scale(length.clone() / two.clone());
+            moments += Self::from_parts(length, first, second).scaled(cross_section.clone());
+        }
+        moments
+    }
+
+    /// The mass weighted moments of a tetrahedral mesh, whose elements are given by the indices
+    /// of their corners (in either orientation), each paired with its density.  These are
+    /// integrals of the density rather than of one, so they are used with a density of one, as
+    /// in [`Self::mass_inv_m`].
+    pub fn from_tetrahedra<I: IntoIterator<Item = ([usize; 4], T)>>(
+        vertices: &[Point3<T>],
+        tetrahedra: I,
+    ) -> Self {
+        let mut moments = Self::zero();
+        for (tetrahedron, density) in tetrahedra {
+            moments += Self::tetrahedron(&tetrahedron.map(|i| vertices[i].clone())).scaled(density);
+        }
+        moments
+    }
+
+    /// The mass weighted moments of a body of uniform `density`.
+    pub fn scaled(self, density: T) -> Self {
+        Moments {
+            v: self.v * density.clone(),
+            x: self.x * density.clone(),
+            y: self.y * density.clone(),
+            z: self.z * density.clone(),
+            xx: self.xx * density.clone(),
+            xy: self.xy * density.clone(),
+            xz: self.xz * density.clone(),
+            yy: self.yy * density.clone(),
+            yz: self.yz * density.clone(),
+            zz: self.zz * density,
+        }
+    }
+
+    /// The moments of a solid of the given volume and centroid, whose second moments about the
+    /// centroid are `covariance` (`∫ (x - c) (x - c)ᵀ`), placed by the rigid motion `pose`.
+    fn from_central(
+        volume: T,
+        center: Vector3<T>,
+        covariance: Matrix3<T>,
+        pose: &Isometry3<T>,
+    ) -> Self {
+        let second = covariance + (&center * center.transpose()).scale(volume.clone());
+        let first = center.scale(volume.clone());
+        Self::from_parts(volume, first, second).transformed(&AffineTransform {
+            translation: pose.translation.vector.clone(),
+            transform: pose.rotation.clone().to_rotation_matrix().into_inner(),
+        })
+    }
+
+    /// The moments with the given volume `∫ 1`, first moments `∫ x` and second moments
+    /// `∫ x xᵀ`.
+    fn from_parts(volume: T, first: Vector3<T>, second: Matrix3<T>) -> Self {
+        Moments {
+            v: volume,
+            x: first.x.clone(),
+            y: first.y.clone(),
+            z: first.z.clone(),
+            xx: second[(0, 0)].clone(),
+            xy: second[(0, 1)].clone(),
+            xz: second[(0, 2)].clone(),
+            yy: second[(1, 1)].clone(),
+            yz: second[(1, 2)].clone(),
+            zz: second[(2, 2)].clone(),
+        }
+    }
+
+    /// The first moments `∫ x`.
+    pub fn first(&self) -> Vector3<T> {
+        Vector3::new(self.x.clone(), self.y.clone(), self.z.clone())
+    }
+
+    /// The (symmetric) second moments `∫ x xᵀ`.
+    pub fn second(&self) -> Matrix3<T> {
+        Matrix3::new(
+            self.xx.clone(),
+            self.xy.clone(),
+            self.xz.clone(),
+            self.xy.clone(),
+            self.yy.clone(),
+            self.yz.clone(),
+            self.xz.clone(),
+            self.yz.clone(),
+            self.zz.clone(),
+        )
+    }
+
+    /// The moments of the body after it has been moved (and deformed) by `transform`, that is
+    /// the integrals over the image `p + A x` of the body, expressed in the new frame.  The
+    /// volume is scaled by `|det A|`, and the second moments are pushed forward to
+    /// `A (∫ x xᵀ) Aᵀ + A (∫ x) pᵀ + p (∫ x)ᵀ Aᵀ + v p pᵀ`.
+    pub fn transformed(&self, transform: &AffineTransform<T>) -> Self {
+        let (a, p) = (&transform.transform, &transform.translation);
+        let jacobian = a.determinant().abs();
+        let first = self.first();
+        let mapped = a * &first;
+        let second = a * self.second() * a.transpose()
+            + &mapped * p.transpose()
+            + p * mapped.transpose()
+            + (p * p.transpose()).scale(self.v.clone());
+        Self::from_parts(
+            self.v.clone() * jacobian.clone(),
+            (mapped + p.scale(self.v.clone())).scale(jacobian.clone()),
+            second.scale(jacobian),
+        )
+    }
+
+    /// A box with the given half extents along its axes, centered on the origin of `pose`.
+    pub fn cuboid(half_extents: &Vector3<T>, pose: &Isometry3<T>) -> Self {
+        let eight = T::from_u32(8).unwrap();
+        let volume = half_extents.product() * eight;
+        let covariance = Matrix3::from_diagonal(
+            &half_extents
+                .component_mul(half_extents)
+                .scale(volume.clone() / T::from_u32(3).unwrap()),
+        );
+        Self::from_central(volume, Vector3::zeros(), covariance, pose)
+    }
+
+    /// An ellipsoid with the given radii along its axes, centered on the origin of `pose`.
+    pub fn ellipsoid(radii: &Vector3<T>, pose: &Isometry3<T>) -> Self {
+        let volume = T::pi() * radii.product() * T::from_u32(4).unwrap() / T::from_u32(3).unwrap();
+        let covariance = Matrix3::from_diagonal(
+            &radii
+                .component_mul(radii)
+                .scale(volume.clone() / T::from_u32(5).unwrap()),
+        );
+        Self::from_central(volume, Vector3::zeros(), covariance, pose)
+    }
+
+    /// A ball centered on the origin of `pose`.
+    pub fn sphere(radius: T, pose: &Isometry3<T>) -> Self {
+        Self::ellipsoid(&Vector3::repeat(radius), pose)
+    }
+
+    /// A cylinder along the `z` axis, reaching `half_height` on either side of the origin of
+    /// `pose`.
+    pub fn cylinder(radius: T, half_height: T, pose: &Isometry3<T>) -> Self {
+        let r2 = radius.clone() * radius;
+        let volume = T::pi() * r2.clone() * (half_height.clone() + half_height.clone());
+        let across = volume.clone() * r2 / T::from_u32(4).unwrap();
+        let along = volume.clone() * half_height.clone() * half_height / T::from_u32(3).unwrap();
+        let covariance = Matrix3::from_diagonal(&Vector3::new(across.clone(), across, along));
+        Self::from_central(volume, Vector3::zeros(), covariance, pose)
+    }
+
+    /// A [`Self::cylinder`] capped with a half ball at each end.
+    pub fn capsule(radius: T, half_height: T, pose: &Isometry3<T>) -> Self {
+        let r2 = radius.clone() * radius.clone();
+        let h = half_height.clone();
+        let (three, four, five) = (
+            T::from_u32(3).unwrap(),
+            T::from_u32(4).unwrap(),
+            T::from_u32(5).unwrap(),
+        );
+        let cylinder = T::pi() * r2.clone() * (h.clone() + h.clone());
+        let ball = T::pi() * r2.clone() * radius.clone() * four.clone() / three.clone();
+        let across =
+            cylinder.clone() * r2.clone() / four + ball.clone() * r2.clone() / five.clone();
+        //  Each half ball is offset by `h` from the center.
+        let along = cylinder.clone() * h.clone() * h.clone() / three
+            + ball.clone() * (h.clone() * h.clone() + r2.clone() / five)
+            + T::pi() * h * r2.clone() * r2;
+        let covariance = Matrix3::from_diagonal(&Vector3::new(across.clone(), across, along));
+        Self::from_central(cylinder + ball, Vector3::zeros(), covariance, pose)
+    }
+
+    /// A cone along the `z` axis, with the center of its base at the origin of `pose`, and its
+    /// apex at `height` above it.
+    pub fn cone(radius: T, height: T, pose: &Isometry3<T>) -> Self {
+        let r2 = radius.clone() * radius;
+        let volume = T::pi() * r2.clone() * height.clone() / T::from_u32(3).unwrap();
+        let across = volume.clone() * r2 * T::from_u32(3).unwrap() / T::from_u32(20).unwrap();
+        let along = volume.clone() * height.clone() * height.clone() * T::from_u32(3).unwrap()
+            / T::from_u32(80).unwrap();
+        let covariance = Matrix3::from_diagonal(&Vector3::new(across.clone(), across, along));
+        let center = Vector3::z().scale(height / T::from_u32(4).unwrap());
+        Self::from_central(volume, center, covariance, pose)
+    }
+
+    /// The tetrahedron with the given corners, in either orientation.
+    pub fn tetrahedron(corners: &[Point3<T>; 4]) -> Self {
+        let [p0, p1, p2, p3] = corners.clone().map(|p| p.coords);
+        let volume =
+            (&p1 - &p0).dot(&(&p2 - &p0).cross(&(&p3 - &p0))).abs() / T::from_u32(6).unwrap();
+        let sum = &p0 + &p1 + &p2 + &p3;
+        //  ∫ x xᵀ = V / 20 (Σₖ pₖ pₖᵀ + s sᵀ), where s is the sum of the corners.
+        let second = (&p0 * p0.transpose()
+            + &p1 * p1.transpose()
+            + &p2 * p2.transpose()
+            + &p3 * p3.transpose()
+            + &sum * sum.transpose())
+        .scale(volume.clone() / T::from_u32(20).unwrap());
+        let first = sum.scale(volume.clone() / T::from_u32(4).unwrap());
+        Self::from_parts(volume, first, second)
+    }
+
+    /// The compacted mass matrix (before the Kronecker product with I_3).
+    pub(crate) fn compact_m(&self, density: T) -> OMatrix<T, Const<4>, Const<4>> {
+        Matrix4::new(
+            self.v.clone(),
+            self.x.clone(),
+            self.y.clone(),
+            self.z.clone(),
+            self.x.clone(),
+            self.xx.clone(),
+            self.xy.clone(),
+            self.xz.clone(),
+            self.y.clone(),
+            self.xy.clone(),
+            self.yy.clone(),
+            self.yz.clone(),
+            self.z.clone(),
+            self.xz.clone(),
+            self.yz.clone(),
+            self.zz.clone(),
+        )
+        .scale(density)
+    }
     pub(crate) fn compact_inv_m(self, density: T) -> CompInvMoments<T> {
+        self.scaled(density).compact_mass_inv_m()
+    }
+    /// The compacted inverse mass matrix of moments that are already mass weighted.
+    pub(crate) fn compact_mass_inv_m(self) -> CompInvMoments<T> {
         let Moments {
             v,
             x,
@@ -61,16 +468,6 @@ impl<T: RealField> Moments<T> {
             yz,
             zz,
         } = self;
-        let v = v * density.clone();
-        let x = x * density.clone();
-        let y = y * density.clone();
-        let z = z * density.clone();
-        let xx = xx * density.clone();
-        let yy = yy * density.clone();
-        let zz = zz * density.clone();
-        let xy = xy * density.clone();
-        let xz = xz * density.clone();
-        let yz = yz * density.clone();
         //  This was derived with sympy
         let two = T::from_u32(2).unwrap();
         let x0 = yz.clone().powi(2);
@@ -181,8 +578,83 @@ impl<T: RealField> Moments<T> {
             m44,
         }
     }
+    /// The inverse mass matrix of a body of uniform `density`, acting on the 12-vectors of
+    /// [`AffineTransform`] (the translation, then the rows of the linear transform).
     pub fn inv_m(self, density: T) -> OMatrix<T, Const<12>, Const<12>> {
-        self.compact_inv_m(density).inv_m()
+        expand_compact(&self.compact_inv_m(density).to_matrix())
+    }
+    /// The inverse mass matrix of moments that are already mass weighted, such as those of
+    /// [`Self::from_tetrahedra`] or [`Self::scaled`].  The same as [`Self::inv_m`] with a density
+    /// of one.
+    pub fn mass_inv_m(self) -> OMatrix<T, Const<12>, Const<12>> {
+        expand_compact(&self.compact_mass_inv_m().to_matrix())
+    }
+}
+
+/// Expands a compact 4×4 matrix, which acts on the right of [`AffineTransform::to_matrix`], into
+/// the 12×12 matrix that acts on the 12-vectors of [`AffineTransform`] in the same way.
+fn expand_compact<T: RealField>(compact: &Matrix4<T>) -> OMatrix<T, Const<12>, Const<12>> {
+    //  The row of the 3×4 matrix that an entry of the 12-vector is in, and its column (zero for
+    //  the translation).
+    let entry = |r: usize| if r < 3 { (r, 0) } else { ((r - 3) / 3, (r - 3) % 3 + 1) };
+    OMatrix::<T, Const<12>, Const<12>>::from_fn(|r, s| {
+        let ((i, k), (j, l)) = (entry(r), entry(s));
+        if i == j {
+            compact[(k, l)].clone()
+        } else {
+            T::zero()
+        }
+    })
+}
+
+/// The moments of the union of two disjoint bodies, such as the parts of a compound body.
+impl<T: RealField> Add for Moments<T> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl<T: RealField> AddAssign for Moments<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.v += rhs.v;
+        self.x += rhs.x;
+        self.y += rhs.y;
+        self.z += rhs.z;
+        self.xx += rhs.xx;
+        self.xy += rhs.xy;
+        self.xz += rhs.xz;
+        self.yy += rhs.yy;
+        self.yz += rhs.yz;
+        self.zz += rhs.zz;
+    }
+}
+
+/// The moments of a body with a part carved out of it, such as a hollow shell.  The removed part
+/// has to lie inside the body.
+impl<T: RealField> Sub for Moments<T> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        self -= rhs;
+        self
+    }
+}
+
+impl<T: RealField> SubAssign for Moments<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.v -= rhs.v;
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+        self.z -= rhs.z;
+        self.xx -= rhs.xx;
+        self.xy -= rhs.xy;
+        self.xz -= rhs.xz;
+        self.yy -= rhs.yy;
+        self.yz -= rhs.yz;
+        self.zz -= rhs.zz;
     }
 }
 
@@ -220,6 +692,9 @@ impl<T: RealField> CompInvMoments<T> {
         )
     }
 
+    /// The full inverse mass matrix, with entry `3 k + i` for row `i` and column `k` of
+    /// [`AffineTransform::to_matrix`].  Unlike [`Moments::inv_m`], this does not act on the
+    /// 12-vectors of [`AffineTransform`].
     pub fn inv_m(self) -> OMatrix<T, Const<12>, Const<12>> {
         /*  The following is equivalent to this: (checked with sympy)
         Matrix4::new(
@@ -232,3 +707,343 @@ impl<T: RealField> CompInvMoments<T> {
         self.to_matrix().kronecker(&Matrix3::identity())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::moments::expand_compact;
+    use crate::{AffineTransform, MeshError, Moments};
+    use nalgebra::{point, Const, Isometry3, Matrix3, OVector, Point3, SMatrix, Vector3};
+    use std::f64::consts::PI;
+
+    fn assert_close(a: &Moments<f64>, b: &Moments<f64>, tolerance: f64) {
+        let fields = |m: &Moments<f64>| [m.v, m.x, m.y, m.z, m.xx, m.xy, m.xz, m.yy, m.yz, m.zz];
+        for (a, b) in fields(a).into_iter().zip(fields(b)) {
+            assert!((a - b).abs() < tolerance, "{a} != {b}");
+        }
+    }
+
+    /// The surface swept by revolving `profile`, a list of `(radius, z)` that starts and ends on
+    /// the `z` axis, about the `z` axis in `n` steps.
+    fn lathe(profile: &[(f64, f64)], n: usize) -> (Vec<Point3<f64>>, Vec<[usize; 3]>) {
+        let rings = profile.len() - 2;
+        let mut vertices = vec![point![0., 0., profile[0].1]];
+        for &(r, z) in &profile[1..=rings] {
+            vertices.extend((0..n).map(|j| {
+                let angle = 2. * PI * j as f64 / n as f64;
+                point![r * angle.cos(), r * angle.sin(), z]
+            }));
+        }
+        vertices.push(point![0., 0., profile[rings + 1].1]);
+        let ring = |i: usize, j: usize| 1 + i * n + j % n;
+        let top = vertices.len() - 1;
+        let mut faces = Vec::new();
+        for j in 0..n {
+            faces.push([0, ring(0, j + 1), ring(0, j)]);
+            for i in 0..rings - 1 {
+                faces.push([ring(i, j), ring(i, j + 1), ring(i + 1, j + 1)]);
+                faces.push([ring(i, j), ring(i + 1, j + 1), ring(i + 1, j)]);
+            }
+            faces.push([ring(rings - 1, j), ring(rings - 1, j + 1), top]);
+        }
+        (vertices, faces)
+    }
+
+    /// A quarter of a circle of radius `r` around `(0, z)`, from `start` to `end` radians above
+    /// the horizontal.
+    fn arc(r: f64, z: f64, start: f64, end: f64, m: usize) -> Vec<(f64, f64)> {
+        Vec::from_iter((0..=m).map(|k| {
+            let angle = start + (end - start) * k as f64 / m as f64;
+            (r * angle.cos(), z + r * angle.sin())
+        }))
+    }
+
+    /// The box from `min` to `max`, with outward facing triangles.
+    fn cuboid(min: Point3<f64>, max: Point3<f64>) -> (Vec<Point3<f64>>, Vec<[usize; 3]>) {
+        let vertices = Vec::from_iter((0..8).map(|i| {
+            point![
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z }
+            ]
+        }));
+        let faces = vec![
+            [0, 2, 1],
+            [1, 2, 3],
+            [4, 5, 6],
+            [5, 7, 6],
+            [0, 1, 4],
+            [1, 5, 4],
+            [2, 6, 3],
+            [3, 6, 7],
+            [0, 4, 2],
+            [2, 4, 6],
+            [1, 3, 5],
+            [3, 7, 5],
+        ];
+        (vertices, faces)
+    }
+
+    #[test]
+    fn cuboid_moments() {
+        let (min, max) = (point![-1., 0.5, 2.], point![1., 2., 2.5]);
+        let (vertices, faces) = cuboid(min, max);
+        let moments = Moments::from_mesh(&vertices, &faces).unwrap();
+        //  The integrals factor over the axes.
+        let first = |k: usize| (max[k] * max[k] - min[k] * min[k]) / 2.;
+        let second = |k: usize| (max[k].powi(3) - min[k].powi(3)) / 3.;
+        let size: Vector3<f64> = max - min;
+        let v = size.x * size.y * size.z;
+        let expected = Moments {
+            v,
+            x: v / size.x * first(0),
+            y: v / size.y * first(1),
+            z: v / size.z * first(2),
+            xx: v / size.x * second(0),
+            xy: v / size.x / size.y * first(0) * first(1),
+            xz: v / size.x / size.z * first(0) * first(2),
+            yy: v / size.y * second(1),
+            yz: v / size.y / size.z * first(1) * first(2),
+            zz: v / size.z * second(2),
+        };
+        for (a, b) in [
+            (moments.v, expected.v),
+            (moments.x, expected.x),
+            (moments.y, expected.y),
+            (moments.z, expected.z),
+            (moments.xx, expected.xx),
+            (moments.xy, expected.xy),
+            (moments.xz, expected.xz),
+            (moments.yy, expected.yy),
+            (moments.yz, expected.yz),
+            (moments.zz, expected.zz),
+        ] {
+            assert!((a - b).abs() < 1e-12, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn tetrahedron_moments() {
+        //  The corner of the unit cube, whose integrals are 1 / 6, 1 / 24, 1 / 60 and 1 / 120.
+        let vertices: [Point3<f64>; 4] = [
+            point![0., 0., 0.],
+            point![1., 0., 0.],
+            point![0., 1., 0.],
+            point![0., 0., 1.],
+        ];
+        let faces = [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]];
+        let moments = Moments::from_mesh(&vertices, &faces).unwrap();
+        assert!((moments.v - 1. / 6.).abs() < 1e-15);
+        assert!((moments.y - 1. / 24.).abs() < 1e-15);
+        assert!((moments.zz - 1. / 60.).abs() < 1e-15);
+        assert!((moments.xz - 1. / 120.).abs() < 1e-15);
+    }
+
+    #[test]
+    fn broken_meshes() {
+        let (vertices, mut faces) = cuboid(point![0., 0., 0.], point![1., 1., 1.]);
+        let inverted = Vec::from_iter(faces.iter().map(|&[a, b, c]| [a, c, b]));
+        assert_eq!(
+            Moments::from_mesh(&vertices, &inverted),
+            Err(MeshError::Inverted)
+        );
+        faces[0] = [0, 1, 2];
+        assert!(matches!(
+            Moments::from_mesh(&vertices, &faces),
+            Err(MeshError::Inconsistent(_))
+        ));
+        faces.pop();
+        assert!(matches!(
+            Moments::from_mesh(&vertices, &faces[1..]),
+            Err(MeshError::Open(_))
+        ));
+    }
+
+    #[test]
+    fn posed_cuboid_matches_mesh() {
+        let pose = Isometry3::new(Vector3::new(1., -2., 3.), Vector3::new(0.3, 0.5, -0.2));
+        let half_extents = Vector3::new(0.5, 1., 1.5);
+        let (vertices, faces) = cuboid(Point3::from(-half_extents), Point3::from(half_extents));
+        let vertices = Vec::from_iter(vertices.iter().map(|x| pose * x));
+        let mesh = Moments::from_mesh(&vertices, &faces).unwrap();
+        assert_close(&Moments::cuboid(&half_extents, &pose), &mesh, 1e-12);
+    }
+
+    #[test]
+    fn tetrahedron_matches_mesh() {
+        let corners = [
+            point![0.1, 0.2, 0.3],
+            point![1.5, -0.2, 0.1],
+            point![0.4, 1.2, -0.3],
+            point![0.2, 0.5, 1.4],
+        ];
+        let faces = [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]];
+        let mesh = Moments::from_mesh(&corners, &faces).unwrap();
+        assert_close(&Moments::tetrahedron(&corners), &mesh, 1e-12);
+        let [a, b, c, d] = corners;
+        assert_close(&Moments::tetrahedron(&[b, a, c, d]), &mesh, 1e-12);
+    }
+
+    #[test]
+    fn round_shapes_match_faceted_meshes() {
+        let pose = Isometry3::new(Vector3::new(-0.5, 0.2, 1.), Vector3::new(1., 0., 0.4));
+        let faceted = |profile: &[(f64, f64)]| {
+            let (vertices, faces) = lathe(profile, 1024);
+            let vertices = Vec::from_iter(vertices.iter().map(|x| pose * x));
+            Moments::from_mesh(&vertices, &faces).unwrap()
+        };
+        let (r, h) = (0.7, 1.2);
+        let cylinder = [(0., -h), (r, -h), (r, h), (0., h)];
+        assert_close(&Moments::cylinder(r, h, &pose), &faceted(&cylinder), 1e-4);
+        let cone = [(0., 0.), (r, 0.), (0., h)];
+        assert_close(&Moments::cone(r, h, &pose), &faceted(&cone), 1e-4);
+        let sphere = arc(r, 0., -PI / 2., PI / 2., 512);
+        assert_close(&Moments::sphere(r, &pose), &faceted(&sphere), 1e-4);
+        let mut capsule = arc(r, -h, -PI / 2., 0., 256);
+        capsule.extend(arc(r, h, 0., PI / 2., 256));
+        assert_close(&Moments::capsule(r, h, &pose), &faceted(&capsule), 1e-4);
+        //  An ellipsoid is a stretched ball.
+        let radii = Vector3::<f64>::new(0.5, 1., 2.);
+        let ball = Moments::sphere(1., &Isometry3::identity());
+        let ellipsoid = Moments::ellipsoid(&radii, &Isometry3::identity());
+        assert!((ellipsoid.v - ball.v * radii.product()).abs() < 1e-12);
+        assert!((ellipsoid.zz - ball.zz * radii.product() * 4.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn layered_tetrahedra() {
+        //  Two unit cubes stacked on top of each other, each split into six tetrahedra along its
+        //  diagonal, with the top one five times as dense.
+        let vertices = Vec::from_iter(
+            (0..12).map(|i| point![(i & 1) as f64, ((i >> 1) & 1) as f64, (i >> 2) as f64]),
+        );
+        let mut tetrahedra = Vec::new();
+        for (layer, density) in [(0, 1.), (4, 5.)] {
+            for [a, b, c] in [
+                [1, 2, 4],
+                [1, 4, 2],
+                [2, 1, 4],
+                [2, 4, 1],
+                [4, 1, 2],
+                [4, 2, 1],
+            ] {
+                tetrahedra.push(([layer, layer + a, layer + a + b, layer + a + b + c], density));
+            }
+        }
+        let moments = Moments::from_tetrahedra(&vertices, tetrahedra);
+        let half = Vector3::repeat(0.5);
+        let bottom = Moments::cuboid(&half, &Isometry3::translation(0.5, 0.5, 0.5));
+        let top = Moments::cuboid(&half, &Isometry3::translation(0.5, 0.5, 1.5)).scaled(5.);
+        let fields = |m: &Moments<f64>| [m.v, m.x, m.y, m.z, m.xx, m.xy, m.xz, m.yy, m.yz, m.zz];
+        for ((a, b), c) in fields(&moments)
+            .into_iter()
+            .zip(fields(&bottom))
+            .zip(fields(&top))
+        {
+            assert!((a - b - c).abs() < 1e-12, "{a} != {b} + {c}");
+        }
+        let inv_m = moments.clone().mass_inv_m();
+        let m = expand_compact(&moments.compact_m(1.));
+        assert!((inv_m * m - SMatrix::<f64, 12, 12>::identity()).norm() < 1e-9);
+        assert!((bottom.clone().scaled(3.).mass_inv_m() - bottom.inv_m(3.)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn transformed_matches_mesh() {
+        let (vertices, faces) = cuboid(point![-1., 0., 0.5], point![0.5, 2., 1.]);
+        let moments = Moments::from_mesh(&vertices, &faces).unwrap();
+        let transform = AffineTransform {
+            translation: Vector3::new(0.3, -1., 2.),
+            transform: Matrix3::new(1.2, 0.3, -0.1, 0.2, 0.8, 0.4, -0.3, 0.1, 1.5),
+        };
+        let vertices = transform.transform_points(&vertices);
+        let mesh = Moments::from_mesh(&vertices, &faces).unwrap();
+        assert_close(&moments.transformed(&transform), &mesh, 1e-12);
+        //  Mirroring keeps the volume positive.
+        let mirror = AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::from_diagonal(&Vector3::new(-1., 1., 1.)),
+        };
+        let mirrored = moments.transformed(&mirror);
+        assert_eq!(mirrored.v, moments.v);
+        assert_eq!(mirrored.x, -moments.x);
+        assert_eq!(mirrored.xy, -moments.xy);
+    }
+
+    #[test]
+    fn compound_and_hollow_bodies() {
+        let half = Vector3::new(0.5, 0.5, 0.5);
+        let left = Moments::cuboid(&half, &Isometry3::translation(-0.5, 0., 0.));
+        let right = Moments::cuboid(&half, &Isometry3::translation(0.5, 0., 0.));
+        let both = Moments::cuboid(&Vector3::new(1., 0.5, 0.5), &Isometry3::identity());
+        assert_close(&(left.clone() + right.clone()), &both, 1e-12);
+        assert_close(&(both - right), &left, 1e-12);
+        let pose = Isometry3::translation(1., 2., 3.);
+        let shell = Moments::sphere(2., &pose) - Moments::sphere(1., &pose);
+        assert!((shell.v - 4. / 3. * PI * 7.).abs() < 1e-12);
+        //  Still centered on the spheres.
+        assert!((shell.first() / shell.v - Vector3::new(1., 2., 3.)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn flat_shell_is_a_slab() {
+        let pose = Isometry3::new(Vector3::new(0.5, 1., -1.), Vector3::new(0.2, -0.7, 0.4));
+        let (w, d, t) = (2., 1., 0.01);
+        let vertices = [
+            point![-w / 2., -d / 2., 0.],
+            point![w / 2., -d / 2., 0.],
+            point![w / 2., d / 2., 0.],
+            point![-w / 2., d / 2., 0.],
+        ]
+        .map(|x| pose * x);
+        let shell = Moments::from_surface(&vertices, &[[0, 1, 2], [0, 3, 2]], t);
+        let slab = Moments::cuboid(&Vector3::new(w / 2., d / 2., t / 2.), &pose);
+        assert_close(&shell, &slab, 1e-12);
+        let inv_m = shell.clone().inv_m(2.);
+        let m = expand_compact(&shell.compact_m(2.));
+        assert!((inv_m * m - SMatrix::<f64, 12, 12>::identity()).norm() < 1e-6);
+    }
+
+    #[test]
+    fn straight_wire_is_a_cylinder() {
+        //  The cylinder is along the `z` axis of the pose.
+        let pose = Isometry3::new(Vector3::new(1., 0., 2.), Vector3::new(0.5, 0.3, -0.1));
+        let (r, h) = (0.02, 1.5);
+        let points = [-h, -0.5, 0.25, h].map(|z| pose * point![0., 0., z]);
+        let wire = Moments::from_polyline(&points, PI * r * r);
+        assert_close(&wire, &Moments::cylinder(r, h, &pose), 1e-12);
+        let inv_m = wire.clone().inv_m(1.);
+        let m = expand_compact(&wire.compact_m(1.));
+        assert!((inv_m * m - SMatrix::<f64, 12, 12>::identity()).norm() < 1e-6);
+    }
+
+    #[test]
+    fn inverse_mass_matrix_acts_on_transforms() {
+        let moments = Moments {
+            v: 1.,
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            xx: 2.,
+            xy: 0.,
+            xz: 0.,
+            yy: 3.,
+            yz: 0.,
+            zz: 4.,
+        };
+        let force = AffineTransform {
+            translation: Vector3::new(1., 2., 3.),
+            transform: Matrix3::from_element(1.),
+        };
+        let acceleration = AffineTransform::from(
+            moments.clone().mass_inv_m() * OVector::<f64, Const<12>>::from(force.clone()),
+        );
+        assert_eq!(acceleration.translation, force.translation);
+        for i in 0..3 {
+            let row = acceleration.transform.row(i);
+            assert!((row - Vector3::new(1. / 2., 1. / 3., 1. / 4.).transpose()).norm() < 1e-12);
+        }
+        //  The same as the compact inverse acting on the right of the 3×4 matrix.
+        let compact = force.to_matrix() * moments.compact_mass_inv_m().to_matrix();
+        assert!((acceleration.to_matrix() - compact).norm() < 1e-12);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::collision_detection::{Segment, TriangleFace, Vertex};
+use crate::collision_detection::{AsPrimitive, Primitive, Segment, TriangleFace, Vertex};
 
 // pub mod hash_grid;
 // pub mod hierarchical_hash_grid;
@@ -59,26 +59,33 @@ pub trait Object<const D: usize, Ctx>: Clone + PartialEq {
 
 #[derive(Clone, Copy, Debug)]
 pub struct UniformAccdContext<RF: RealField> {
-    step_size: RF,
-    offset: RF,
+    /// The time over which the primitives move along their velocities.
+    pub step_size: RF,
+    /// How far the bounding boxes are dilated in every direction.
+    pub offset: RF,
 }
 
 #[derive(Debug)]
 pub struct IndexedAccdContext<'a, Ctx: Clone, Col> {
-    context: Ctx,
-    collection: &'a Col,
+    /// The context of the items of the collection.
+    pub context: Ctx,
+    /// The collection that the [`Index`]es refer to.
+    pub collection: &'a Col,
 }
 
 /// The type of a geometric element that is part of a collider.  It implements the `interacts_with`
 /// method so that items part of the same collider (`G`) do not interact, but others do.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct SubCollider<T, G=u32> {
-    object: T,
-    collider: G,
+    /// The geometric element.
+    pub object: T,
+    /// The collider that the element is part of.
+    pub collider: G,
 }
 
+/// Refers to an element of the collection of an [`IndexedAccdContext`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Index<T>(T);
+pub struct Index<T>(pub T);
 
 impl<const D: usize, RF: RealField> Object<D, UniformAccdContext<RF>> for Vertex<RF, D> {
     type RF = RF;
@@ -276,7 +283,88 @@ impl<const D: usize, T: Object<D, Ctx>, Ctx, G: Clone + Eq> Object<D, Ctx> for S
     }
 }
 
-impl<'a, Ctx: Copy, Col> Clone for IndexedAccdContext<'a, Ctx, Col> {
+impl<RF: RealField> Object<3, UniformAccdContext<RF>> for Primitive<RF> {
+    type RF = RF;
+
+    fn aabb_min(&self, context: UniformAccdContext<RF>) -> Point<Self::RF, 3> {
+        match self {
+            Primitive::Vertex(v) => v.aabb_min(context),
+            Primitive::Segment(s) => s.aabb_min(context),
+            Primitive::Face(f) => f.aabb_min(context),
+        }
+    }
+
+    fn aabb_max(&self, context: UniformAccdContext<RF>) -> Point<Self::RF, 3> {
+        match self {
+            Primitive::Vertex(v) => v.aabb_max(context),
+            Primitive::Segment(s) => s.aabb_max(context),
+            Primitive::Face(f) => f.aabb_max(context),
+        }
+    }
+
+    fn aabb_min_index(&self, context: UniformAccdContext<RF>, i: usize) -> Self::RF {
+        match self {
+            Primitive::Vertex(v) => v.aabb_min_index(context, i),
+            Primitive::Segment(s) => s.aabb_min_index(context, i),
+            Primitive::Face(f) => f.aabb_min_index(context, i),
+        }
+    }
+
+    fn aabb_max_index(&self, context: UniformAccdContext<RF>, i: usize) -> Self::RF {
+        match self {
+            Primitive::Vertex(v) => v.aabb_max_index(context, i),
+            Primitive::Segment(s) => s.aabb_max_index(context, i),
+            Primitive::Face(f) => f.aabb_max_index(context, i),
+        }
+    }
+
+    fn interacts_with(&self, _: &Self, _: UniformAccdContext<RF>) -> bool {
+        true
+    }
+}
+
+impl<RF: RealField> AsPrimitive<UniformAccdContext<RF>> for Vertex<RF, 3> {
+    fn as_primitive(&self, _: UniformAccdContext<RF>) -> Primitive<RF> {
+        Primitive::Vertex(self.clone())
+    }
+}
+
+impl<RF: RealField> AsPrimitive<UniformAccdContext<RF>> for Segment<RF, 3> {
+    fn as_primitive(&self, _: UniformAccdContext<RF>) -> Primitive<RF> {
+        Primitive::Segment(self.clone())
+    }
+}
+
+impl<RF: RealField> AsPrimitive<UniformAccdContext<RF>> for TriangleFace<RF, 3> {
+    fn as_primitive(&self, _: UniformAccdContext<RF>) -> Primitive<RF> {
+        Primitive::Face(self.clone())
+    }
+}
+
+impl<RF: RealField> AsPrimitive<UniformAccdContext<RF>> for Primitive<RF> {
+    fn as_primitive(&self, _: UniformAccdContext<RF>) -> Primitive<RF> {
+        self.clone()
+    }
+}
+
+impl<'a, C, I, Ctx: Clone> AsPrimitive<IndexedAccdContext<'a, Ctx, C>> for Index<I>
+where
+    C: std::ops::Index<I>,
+    I: Clone + PartialEq,
+    C::Output: AsPrimitive<Ctx>,
+{
+    fn as_primitive(&self, context: IndexedAccdContext<Ctx, C>) -> Primitive<Self::RF> {
+        context.collection[self.0.clone()].as_primitive(context.context)
+    }
+}
+
+impl<T: AsPrimitive<Ctx>, Ctx, G: Clone + Eq> AsPrimitive<Ctx> for SubCollider<T, G> {
+    fn as_primitive(&self, context: Ctx) -> Primitive<Self::RF> {
+        self.object.as_primitive(context)
+    }
+}
+
+impl<'a, Ctx: Clone, Col> Clone for IndexedAccdContext<'a, Ctx, Col> {
     fn clone(&self) -> Self {
         Self {
             context: self.context.clone(),
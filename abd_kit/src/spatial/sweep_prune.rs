@@ -54,7 +54,7 @@ impl<const D: usize, T> SweepPrune<D, T> {
                         Kind::Max => a.item.aabb_max_index(context.clone(), i),
                     };
                     let b = match b.kind {
-                        Kind::Min => b.item.aabb_max_index(context.clone(), i),
+                        Kind::Min => b.item.aabb_min_index(context.clone(), i),
                         Kind::Max => b.item.aabb_max_index(context.clone(), i),
                     };
                     a.partial_cmp(&b).unwrap()
@@ -153,6 +153,14 @@ impl<'a, const D: usize, T: Object<D, Ctx>, Ctx: Clone> Iterator
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
+            //  Only the minimum of an item starts a sweep.
+            if let Some(AxisElement {
+                kind: Kind::Max, ..
+            }) = self.axis.first()
+            {
+                self.axis = &self.axis[1..];
+                continue;
+            }
             if let (Some(AxisElement { item, .. }), Some(AxisElement { item: other, kind })) =
                 (&self.axis.get(0), &self.axis.get(self.other_index))
             {
@@ -167,7 +175,7 @@ impl<'a, const D: usize, T: Object<D, Ctx>, Ctx: Clone> Iterator
                             .le(&other.aabb_max(self.context.clone()))
                         && item
                             .aabb_max(self.context.clone())
-                            .ge(&other.aabb_max(self.context.clone()))
+                            .ge(&other.aabb_min(self.context.clone()))
                     {
                         self.other_index += 1;
                         return Some((item.clone(), other.clone()));
@@ -197,6 +205,14 @@ impl<'a, const D: usize, T: Object<D, Ctx>, Ctx: Clone> Iterator
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
+            //  Only the minimum of an item starts a sweep.
+            if let Some(AxisElement {
+                kind: Kind::Max, ..
+            }) = self.axis.first()
+            {
+                self.axis = &self.axis[1..];
+                continue;
+            }
             if let (Some(AxisElement { item, .. }), Some(AxisElement { item: other, kind })) =
                 (&self.axis.get(0), &self.axis.get(self.other_index))
             {
@@ -215,7 +231,7 @@ impl<'a, const D: usize, T: Object<D, Ctx>, Ctx: Clone> Iterator
                             .le(&other.aabb_max(self.context.clone()))
                             && item
                             .aabb_max(self.context.clone())
-                            .ge(&other.aabb_max(self.context.clone()))
+                            .ge(&other.aabb_min(self.context.clone()))
                         {
                             return Some((self.a_idx, self.b_idx));
                         }
@@ -1,7 +1,9 @@
-use crate::collision_detection::{distance_squared, Vertex};
+use crate::collision_detection::{
+    distance_squared, minimum_time_step, Primitive, Segment, TriangleFace, Vertex,
+};
 use crate::spatial::sweep_prune::SweepPrune;
-use crate::spatial::{IndexedAccdContext, Object, SpatialDB, UniformAccdContext};
-use nalgebra::{Point3, Vector3};
+use crate::spatial::{Index, IndexedAccdContext, Object, SpatialDB, UniformAccdContext};
+use nalgebra::{point, vector, Point3, Vector3};
 use rand::prelude::StdRng;
 use rand::{Rng, SeedableRng};
 use std::array;
@@ -29,7 +31,7 @@ fn aabb_aabb_vertex_3d() {
                 context: uniform_context,
                 collection: &vertices,
             },
-            0..vertices.len(),
+            (0..vertices.len()).map(Index),
         );
         broad.check_overlap_count();
         for _ in 0..10 {
@@ -40,16 +42,16 @@ fn aabb_aabb_vertex_3d() {
             let pairs: hashbrown::HashSet<(usize, usize)> = hashbrown::HashSet::from_iter(
                 broad
                     .self_close_pairs(context)
-                    .map(|(a, b)| (a.min(b), a.max(b))),
+                    .map(|(Index(a), Index(b))| (a.min(b), a.max(b))),
             );
             for i in 0..vertices.len() {
                 for j in (i + 1)..vertices.len() {
                     assert_eq!(
                         distance_squared::aabb_aabb(
-                            &i.aabb_min(context),
-                            &i.aabb_max(context),
-                            &j.aabb_min(context),
-                            &j.aabb_max(context)
+                            &Index(i).aabb_min(context),
+                            &Index(i).aabb_max(context),
+                            &Index(j).aabb_min(context),
+                            &Index(j).aabb_max(context)
                         ) == 0.,
                         pairs.contains(&(i, j))
                     );
@@ -66,3 +68,86 @@ fn aabb_aabb_vertex_3d() {
         }
     }
 }
+
+#[test]
+fn sweep_prune_finds_overlapping_boxes() {
+    //  Boxes from `x` to `x + v`, with distinct minima and maxima along every axis.
+    let vertices = [
+        (point![0., 0., 0.], vector![2., 2., 2.]),
+        (point![1., 1., 1.], vector![2., 2., 2.]),
+        (point![2.5, 2.5, 2.5], vector![1.5, 1.5, 1.5]),
+        (point![10., 0.5, 0.5], vector![1., 1., 1.]),
+    ]
+    .map(|(x, v)| Vertex { x, v });
+    let context = IndexedAccdContext {
+        context: UniformAccdContext {
+            step_size: 1.,
+            offset: 0.,
+        },
+        collection: &vertices,
+    };
+    let broad = SweepPrune::<3, _>::from_iter(context, (0..vertices.len()).map(Index));
+    broad.check_overlap_count();
+    let sorted = |a: usize, b: usize| (a.min(b), a.max(b));
+    let pairs = hashbrown::HashSet::<(usize, usize)>::from_iter(
+        broad
+            .self_close_pairs(context)
+            .map(|(Index(a), Index(b))| sorted(a, b)),
+    );
+    assert_eq!(pairs, hashbrown::HashSet::from_iter([(0, 1), (1, 2)]));
+    let items = Vec::from_iter(
+        SpatialDB::<3, IndexedAccdContext<_, [Vertex<f64, 3>; 4]>>::all_items(&broad)
+            .map(|Index(i)| i),
+    );
+    let indices = hashbrown::HashSet::<(usize, usize)>::from_iter(
+        broad
+            .self_close_indices(context)
+            .map(|(a, b)| sorted(items[a], items[b])),
+    );
+    assert_eq!(indices, pairs);
+}
+
+#[test]
+fn minimum_time_step_stops_before_contact() {
+    let vertex = |x: Point3<f64>, v: Vector3<f64>| Vertex { x, v };
+    let still = Vector3::zeros();
+    let mut primitives = vec![
+        //  Falls onto the triangle at half the time step.
+        Primitive::Vertex(vertex(point![0., 0., 1.], vector![0., 0., -2.])),
+        Primitive::Face(TriangleFace {
+            a: vertex(point![-1., -1., 0.], still),
+            b: vertex(point![1., -1., 0.], still),
+            c: vertex(point![0., 1., 0.], still),
+        }),
+        //  Moves parallel to the triangle, and never gets close.
+        Primitive::Vertex(vertex(point![0., 0., -1.], vector![1., 0., 0.])),
+        //  Hit each other at a quarter of the time step.
+        Primitive::Segment(Segment(
+            vertex(point![5., -1., 0.], still),
+            vertex(point![5., 1., 0.], still),
+        )),
+        Primitive::Segment(Segment(
+            vertex(point![4., 0., 1.], vector![0., 0., -4.]),
+            vertex(point![6., 0., 1.], vector![0., 0., -4.]),
+        )),
+    ];
+    let context = UniformAccdContext {
+        step_size: 1.,
+        offset: 1e-3,
+    };
+    let step = |primitives: &Vec<Primitive<f64>>| {
+        let context = IndexedAccdContext {
+            context,
+            collection: primitives,
+        };
+        let broad = SweepPrune::<3, _>::from_iter(context, (0..primitives.len()).map(Index));
+        minimum_time_step(1., 1e-3, &broad, context)
+    };
+    let fraction = step(&primitives);
+    assert!(0.2 < fraction && fraction < 0.25, "{fraction}");
+    primitives.pop();
+    let fraction = step(&primitives);
+    assert!(0.4 < fraction && fraction < 0.5, "{fraction}");
+    primitives.remove(0);
+    assert_eq!(step(&primitives), 1.);
+}
@@ -0,0 +1,734 @@
+//! A minimal physics engine, put together from the pieces in [`kinematics`](crate::kinematics),
+//! [`spatial`](crate::spatial) and [`collision_detection`](crate::collision_detection).
+//!
+//! Every step the vertices, edges and faces of the bodies are swept from their current to their
+//! predicted state, close pairs of them are found with a [`SweepPrune`], and the close pairs of
+//! each pair of bodies are collected into a barrier [`Contact`] with lagged [`Friction`].  The
+//! isles are then advanced with the [`NewtonSolver`], with the barrier stiffness chosen by
+//! [`AdaptiveStiffness`].
+
+use crate::collision_detection::{minimum_time_step, Primitive, Segment, TriangleFace, Vertex};
+use crate::kinematics::contact::{Contact, ContactPrimitives};
+use crate::kinematics::friction::Friction;
+use crate::kinematics::{
+    AdaptiveStiffness, Barrier, Blocked, Body, InteractingObjects, NewtonSolver, ObjectRef,
+};
+use crate::spatial::sweep_prune::SweepPrune;
+use crate::spatial::{
+    Index, IndexedAccdContext, Object, SpatialDB, SubCollider, UniformAccdContext,
+};
+use crate::{AffineTransform, MeshError, Moments};
+use hashbrown::HashMap;
+
+/// Identifies a body of a [`World`].  Handles are never reused, so a handle of a removed body
+/// stays invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BodyHandle(u64);
+
+/// A closed triangle mesh, in the body space of its body, used for contact.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh<RF: RealField> {
+    vertices: Vec<Point3<RF>>,
+    faces: Vec<[usize; 3]>,
+    edges: Vec<[usize; 2]>,
+}
+
+/// How a body of a [`World`] moves.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Motion<RF: RealField> {
+    /// Moved by its inertia, its forces and contact.
+    Dynamic {
+        /// The (unscaled) volume integrals of the body.
+        moments: Moments<RF>,
+        /// The uniform density that scales the moments, or one if they are mass weighted.
+        density: RF,
+    },
+    /// Never moves.
+    Static,
+    /// Moves with its velocity, which is left to the user to change.
+    Kinematic,
+}
+
+/// A body owned by a [`World`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AffineBody<RF: RealField> {
+    /// The affine transform from body space to world space.
+    pub state: AffineTransform<RF>,
+    /// The rate of change of the state.
+    pub velocity: AffineTransform<RF>,
+    /// The generalized external forces acting on the body.  Ignored for bodies that are not
+    /// dynamic.
+    pub forces: AffineTransform<RF>,
+    /// How the body moves.
+    pub motion: Motion<RF>,
+    /// The surface of the body, in body space.
+    pub mesh: Mesh<RF>,
+}
+
+/// Owns a set of bodies, and advances them in time.
+#[derive(Clone, Debug)]
+pub struct World<RF: RealField> {
+    /// The distance `d̂` below which bodies push each other apart.
+    pub barrier_thickness: RF,
+    /// The stiffness of the orthogonality potential, which keeps the bodies close to rigid.
+    pub orthogonal_stiffness: RF,
+    /// The coefficient of friction `μ` between bodies, unless it is set for their pair with
+    /// [`Self::set_pair_friction`].
+    pub friction: RF,
+    /// The relative speed `ε_v` below which touching bodies are considered to be sticking.
+    pub sliding_speed: RF,
+    /// The solver that advances the isles.
+    pub solver: NewtonSolver<RF>,
+    bodies: Vec<(BodyHandle, AffineBody<RF>)>,
+    pair_friction: HashMap<(BodyHandle, BodyHandle), RF>,
+    next_handle: u64,
+    stiffness: Option<AdaptiveStiffness<RF>>,
+}
+
+/// The outcome of [`World::step`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepReport<RF> {
+    /// The number of pairs of bodies that were close enough to come into contact.
+    pub body_pairs: usize,
+    /// The number of pairs of primitives that were close enough to come into contact.
+    pub candidates: usize,
+    /// The number of pairs of primitives that are closer than the barrier thickness at the end
+    /// of the step.
+    pub contacts: usize,
+    /// The number of isles that were solved.
+    pub isles: usize,
+    /// The total number of Newton iterations over all the isles.
+    pub iterations: usize,
+    /// Whether the Newton iterations converged for every isle.
+    pub converged: bool,
+    /// The barrier stiffness `κ` used for the step, if there have been any contacts so far.
+    pub stiffness: Option<RF>,
+    /// The largest [`AffineTransform::drift`] of the dynamic bodies at the end of the step.  If
+    /// it keeps growing, [`World::orthogonal_stiffness`] is too low.
+    pub drift: RF,
+    /// The fraction of the step that the bodies could move along their unconstrained paths
+    /// before any two of them come within the barrier thickness, as given by
+    /// [`minimum_time_step`].  If it is often much smaller than one, the time step is too large
+    /// for the contacts to be found reliably.
+    pub time_of_impact: RF,
+}
+
+/// Why [`World::step`] rejected a step, which leaves the bodies as they were.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepError {
+    /// The kinematic bodies with these handles can not reach the states that their velocities
+    /// prescribe without passing through the dynamic bodies in their way (see [`Blocked`]).
+    Blocked(Vec<BodyHandle>),
+}
+
+impl std::fmt::Display for StepError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepError::Blocked(handles) => {
+                write!(f, "the kinematic bodies {handles:?} are blocked")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StepError {}
+
+/// A vertex, edge or face of a mesh, as the indices of its vertices.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Part {
+    Vertex(usize),
+    Edge([usize; 2]),
+    Face([usize; 3]),
+}
+
+impl<RF: RealField> Mesh<RF> {
+    /// A mesh with the given vertices and faces, each face being the indices of its three
+    /// vertices in counter-clockwise order when seen from the outside.  The edges are found from
+    /// the faces.
+    pub fn new(vertices: Vec<Point3<RF>>, faces: Vec<[usize; 3]>) -> Self {
+        let mut edges = Vec::from_iter(
+            faces
+                .iter()
+                .flat_map(|&[a, b, c]| [[a, b], [b, c], [c, a]])
+                .map(|[a, b]| [a.min(b), a.max(b)]),
+        );
+        edges.sort_unstable();
+        edges.dedup();
+        Self {
+            vertices,
+            faces,
+            edges,
+        }
+    }
+
+    /// The vertices, in body space.
+    pub fn vertices(&self) -> &[Point3<RF>] {
+        &self.vertices
+    }
+
+    /// The faces, as indices into [`Self::vertices`].
+    pub fn faces(&self) -> &[[usize; 3]] {
+        &self.faces
+    }
+
+    /// The unique edges of the faces.
+    pub fn edges(&self) -> &[[usize; 2]] {
+        &self.edges
+    }
+
+    /// The moments of the solid bounded by the mesh, for [`Motion::Dynamic`].  See
+    /// [`Moments::from_mesh`].
+    pub fn moments(&self) -> Result<Moments<RF>, MeshError> {
+        Moments::from_mesh(&self.vertices, &self.faces)
+    }
+}
+
+impl<RF: RealField> World<RF> {
+    /// An empty world.
+    pub fn new(barrier_thickness: RF) -> Self {
+        Self {
+            barrier_thickness,
+            orthogonal_stiffness: RF::from_f64(1e6).unwrap(),
+            friction: RF::zero(),
+            sliding_speed: RF::from_f64(1e-3).unwrap(),
+            solver: NewtonSolver::default(),
+            bodies: Vec::new(),
+            pair_friction: HashMap::new(),
+            next_handle: 0,
+            stiffness: None,
+        }
+    }
+
+    /// Adds a body, returning the handle that refers to it from now on.
+    pub fn add_body(&mut self, body: AffineBody<RF>) -> BodyHandle {
+        let handle = BodyHandle(self.next_handle);
+        self.next_handle += 1;
+        self.bodies.push((handle, body));
+        handle
+    }
+
+    /// Removes a body, returning it if it was part of this world.
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<AffineBody<RF>> {
+        let index = self.index_of(handle)?;
+        self.pair_friction
+            .retain(|&(a, b), _| a != handle && b != handle);
+        Some(self.bodies.remove(index).1)
+    }
+
+    /// The body with the given handle, if it is part of this world.
+    pub fn body(&self, handle: BodyHandle) -> Option<&AffineBody<RF>> {
+        self.index_of(handle).map(|i| &self.bodies[i].1)
+    }
+
+    /// The body with the given handle, if it is part of this world, for changing its state,
+    /// velocity or forces between steps.
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut AffineBody<RF>> {
+        self.index_of(handle).map(|i| &mut self.bodies[i].1)
+    }
+
+    /// All the bodies, in the order they were added.
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &AffineBody<RF>)> + '_ {
+        self.bodies.iter().map(|(handle, body)| (*handle, body))
+    }
+
+    /// The number of bodies.
+    pub fn len(&self) -> usize {
+        self.bodies.len()
+    }
+
+    /// Whether there are no bodies.
+    pub fn is_empty(&self) -> bool {
+        self.bodies.is_empty()
+    }
+
+    /// Sets the coefficient of friction between the bodies `a` and `b`, in place of
+    /// [`Self::friction`].  With `None`, the pair goes back to [`Self::friction`].
+    pub fn set_pair_friction(&mut self, a: BodyHandle, b: BodyHandle, friction: Option<RF>) {
+        let pair = (a.min(b), a.max(b));
+        match friction {
+            Some(friction) => self.pair_friction.insert(pair, friction),
+            None => self.pair_friction.remove(&pair),
+        };
+    }
+
+    /// The coefficient of friction between the bodies `a` and `b`.
+    pub fn pair_friction(&self, a: BodyHandle, b: BodyHandle) -> RF {
+        self.pair_friction
+            .get(&(a.min(b), a.max(b)))
+            .unwrap_or(&self.friction)
+            .clone()
+    }
+
+    /// The barrier stiffness `κ` that will be used for the next step, if there have been any
+    /// contacts so far.
+    pub fn stiffness(&self) -> Option<RF> {
+        self.stiffness.as_ref().map(AdaptiveStiffness::stiffness)
+    }
+
+    /// Advances all the bodies by `time_step`.
+    ///
+    /// The contacts are only looked for between primitives that are close to the paths that the
+    /// bodies would take if they were unconstrained, so a body that is pushed much further than
+    /// that within a single step can pass through others.
+    ///
+    /// The kinematic bodies always move with their velocities.  If that would take them through
+    /// a dynamic body that can not get out of their way, the step is rejected instead.
+    pub fn step(&mut self, time_step: RF) -> Result<StepReport<RF>, StepError> {
+        let h = time_step.clone();
+        let dhat = self.barrier_thickness.clone();
+        let bodies = Vec::from_iter(self.bodies.iter().map(|(_, body)| match &body.motion {
+            Motion::Dynamic { moments, density } => Body::Dynamic(ObjectRef {
+                state: &body.state,
+                velocity: &body.velocity,
+                moments,
+                forces: &body.forces,
+                density: density.clone(),
+            }),
+            Motion::Static => Body::Static(&body.state),
+            Motion::Kinematic => Body::Kinematic {
+                state: &body.state,
+                target: body.state.clone() + body.velocity.scaled_by(h.clone()),
+            },
+        }));
+        let start = Vec::from_iter(bodies.iter().map(|body| body.state().clone()));
+        let predicted = Vec::from_iter(bodies.iter().map(|body| match body {
+            Body::Dynamic(object) => object.predicted_state(h.clone()),
+            _ => body.prescribed_state().unwrap(),
+        }));
+        //  The primitives of all the bodies in world space, moving from their current to their
+        //  predicted positions over the step.  The bodies that are not dynamic share a collider,
+        //  as they never push each other.
+        let mut parts = Vec::new();
+        let mut primitives = Vec::new();
+        let mut items = Vec::new();
+        for (i, (_, body)) in self.bodies.iter().enumerate() {
+            let vertices = Vec::from_iter(body.mesh.vertices.iter().map(|x| {
+                let x0 = start[i].transform_point(x);
+                let v = predicted[i].transform_point(x) - &x0;
+                Vertex { x: x0, v }
+            }));
+            let collider = if bodies[i].is_dynamic() {
+                i
+            } else {
+                usize::MAX
+            };
+            let mesh_parts = (0..vertices.len())
+                .map(Part::Vertex)
+                .chain(body.mesh.edges.iter().copied().map(Part::Edge))
+                .chain(body.mesh.faces.iter().copied().map(Part::Face));
+            for part in mesh_parts {
+                items.push(SubCollider {
+                    object: Index(primitives.len()),
+                    collider,
+                });
+                primitives.push(match part {
+                    Part::Vertex(a) => Primitive::Vertex(vertices[a].clone()),
+                    Part::Edge([a, b]) => {
+                        Primitive::Segment(Segment(vertices[a].clone(), vertices[b].clone()))
+                    }
+                    Part::Face([a, b, c]) => Primitive::Face(TriangleFace {
+                        a: vertices[a].clone(),
+                        b: vertices[b].clone(),
+                        c: vertices[c].clone(),
+                    }),
+                });
+                parts.push((i, part));
+            }
+        }
+        //  Boxes dilated by half the barrier thickness overlap when they are within it.
+        let uniform = UniformAccdContext {
+            step_size: RF::one(),
+            offset: dhat.clone() / RF::from_u32(2).unwrap(),
+        };
+        let context = IndexedAccdContext {
+            context: uniform.clone(),
+            collection: &primitives,
+        };
+        let scene_scale = primitives
+            .iter()
+            .map(|p| (p.aabb_min(uniform.clone()), p.aabb_max(uniform.clone())))
+            .reduce(|(a_min, a_max), (min, max)| (a_min.inf(&min), a_max.sup(&max)))
+            .map_or(RF::one(), |(min, max)| (max - min).norm());
+        let db = SweepPrune::<3, _>::from_iter(context.clone(), items);
+
+        let mut close = HashMap::<(usize, usize), Contact<RF>>::new();
+        for (a, b) in db.self_close_pairs(context.clone()) {
+            let (mut a, mut b) = (parts[a.object.0], parts[b.object.0]);
+            if a.0 > b.0 {
+                std::mem::swap(&mut a, &mut b);
+            }
+            let ((i, a), (j, b)) = (a, b);
+            let (mesh_a, mesh_b) = (&self.bodies[i].1.mesh, &self.bodies[j].1.mesh);
+            let primitive = match (a, b) {
+                (Part::Vertex(v), Part::Face(f)) => ContactPrimitives::VertexFace(
+                    mesh_a.vertices[v].clone(),
+                    f.map(|k| mesh_b.vertices[k].clone()),
+                ),
+                (Part::Face(f), Part::Vertex(v)) => ContactPrimitives::FaceVertex(
+                    f.map(|k| mesh_a.vertices[k].clone()),
+                    mesh_b.vertices[v].clone(),
+                ),
+                (Part::Edge(e_a), Part::Edge(e_b)) => ContactPrimitives::EdgeEdge(
+                    e_a.map(|k| mesh_a.vertices[k].clone()),
+                    e_b.map(|k| mesh_b.vertices[k].clone()),
+                ),
+                _ => continue,
+            };
+            close
+                .entry((i, j))
+                .or_insert_with(|| Contact::new(dhat.clone(), RF::one()))
+                .primitives
+                .push(primitive);
+        }
+        let mut contacts = Vec::from_iter(close);
+        contacts.sort_unstable_by_key(|(pair, _)| *pair);
+        let time_of_impact = minimum_time_step(RF::one(), dhat.clone(), &db, context.clone());
+        if self.stiffness.is_none() && !contacts.is_empty() {
+            let close = HashMap::from_iter(contacts.iter().map(|(pair, contact)| (*pair, contact)));
+            let objects = InteractingObjects::from_bodies(
+                bodies.clone(),
+                close,
+                self.orthogonal_stiffness.clone(),
+            );
+            self.stiffness = Some(AdaptiveStiffness::new(
+                &objects,
+                &start,
+                h.clone(),
+                dhat.clone(),
+                scene_scale,
+            ));
+        }
+        let stiffness = self.stiffness();
+        let threshold = self.sliding_speed.clone() * h.clone();
+        let pairs = Vec::from_iter(contacts.into_iter().map(|(pair, mut contact)| {
+            if let Some(stiffness) = &stiffness {
+                contact.set_stiffness(stiffness.clone());
+            }
+            let friction = Friction::lagged(
+                &contact,
+                &start[pair.0],
+                &start[pair.1],
+                self.pair_friction(self.bodies[pair.0].0, self.bodies[pair.1].0),
+                threshold.clone(),
+            );
+            (pair, (contact, friction))
+        }));
+
+        let objects = InteractingObjects::from_bodies(
+            bodies,
+            HashMap::from_iter(pairs.iter().map(|(pair, potential)| (*pair, potential))),
+            self.orthogonal_stiffness.clone(),
+        );
+        let isles = objects.create_isles(&db, context, |item| parts[item.object.0].0);
+        let reports = self.solver.solve_isles(&isles, h.clone());
+        let mut blocked = Vec::new();
+        for (isle, report) in isles.iter().zip(&reports) {
+            if let Err(Blocked { kinematic }) = report {
+                blocked.extend(kinematic.iter().map(|&k| self.bodies[isle.indices()[k]].0));
+            }
+        }
+        if !blocked.is_empty() {
+            //  A kinematic body can be part of more than one isle.
+            blocked.sort_unstable();
+            blocked.dedup();
+            return Err(StepError::Blocked(blocked));
+        }
+        let mut end = predicted;
+        let mut velocities = Vec::from_iter(self.bodies.iter().map(|(_, b)| b.velocity.clone()));
+        let (mut iterations, mut converged) = (0, true);
+        for (isle, report) in isles.iter().zip(reports.into_iter().flatten()) {
+            iterations += report.iterations;
+            converged &= report.converged;
+            for ((&i, state), velocity) in
+                isle.indices().iter().zip(report.state).zip(report.velocity)
+            {
+                //  The velocities of the kinematic bodies are left as the user set them.
+                match self.bodies[i].1.motion {
+                    Motion::Dynamic { .. } => {
+                        end[i] = state;
+                        velocities[i] = velocity;
+                    }
+                    Motion::Kinematic => end[i] = state,
+                    Motion::Static => {}
+                }
+            }
+        }
+        if let Some(stiffness) = &mut self.stiffness {
+            stiffness.update(&objects, &end);
+        }
+        let report = StepReport {
+            body_pairs: pairs.len(),
+            candidates: pairs.iter().map(|(_, (c, _))| c.primitives.len()).sum(),
+            contacts: pairs
+                .iter()
+                .map(|((a, b), (c, _))| c.active(&end[*a], &end[*b]))
+                .sum(),
+            isles: isles.len(),
+            iterations,
+            converged,
+            stiffness,
+            drift: end
+                .iter()
+                .zip(&self.bodies)
+                .filter(|(_, (_, body))| matches!(body.motion, Motion::Dynamic { .. }))
+                .fold(RF::zero(), |acc, (q, _)| acc.max(q.drift())),
+            time_of_impact,
+        };
+        drop(isles);
+        drop(objects);
+        for ((_, body), (state, velocity)) in
+            self.bodies.iter_mut().zip(end.into_iter().zip(velocities))
+        {
+            if body.motion != Motion::Static {
+                body.state = state;
+                body.velocity = velocity;
+            }
+        }
+        Ok(report)
+    }
+
+    fn index_of(&self, handle: BodyHandle) -> Option<usize> {
+        self.bodies.binary_search_by_key(&handle, |(h, _)| *h).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::{AffineBody, Mesh, Motion, StepError, World};
+    use crate::{AffineTransform, MeshError, Moments};
+    use nalgebra::{point, Matrix3, Vector3};
+
+    /// The unit cube centered on the origin.
+    fn cube() -> (Mesh<f64>, Moments<f64>) {
+        let vertices = Vec::from_iter((0..8).map(|i| {
+            point![
+                (i & 1) as f64 - 0.5,
+                ((i >> 1) & 1) as f64 - 0.5,
+                ((i >> 2) & 1) as f64 - 0.5
+            ]
+        }));
+        let faces = vec![
+            [0, 2, 1],
+            [1, 2, 3],
+            [4, 5, 6],
+            [5, 7, 6],
+            [0, 1, 4],
+            [1, 5, 4],
+            [2, 6, 3],
+            [3, 6, 7],
+            [0, 4, 2],
+            [2, 4, 6],
+            [1, 3, 5],
+            [3, 7, 5],
+        ];
+        let moments = Moments {
+            v: 1.,
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            xx: 1. / 12.,
+            xy: 0.,
+            xz: 0.,
+            yy: 1. / 12.,
+            yz: 0.,
+            zz: 1. / 12.,
+        };
+        (Mesh::new(vertices, faces), moments)
+    }
+
+    /// A square static floor, ten wide, at `z = 0`.
+    fn floor() -> Mesh<f64> {
+        Mesh::new(
+            vec![
+                point![-5., -5., 0.],
+                point![5., -5., 0.],
+                point![5., 5., 0.],
+                point![-5., 5., 0.],
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+    }
+
+    fn at(translation: Vector3<f64>) -> AffineTransform<f64> {
+        AffineTransform {
+            translation,
+            transform: Matrix3::identity(),
+        }
+    }
+
+    fn still() -> AffineTransform<f64> {
+        AffineTransform {
+            translation: Vector3::zeros(),
+            transform: Matrix3::zeros(),
+        }
+    }
+
+    #[test]
+    fn handles_survive_removal() {
+        let (mesh, _) = cube();
+        let mut world = World::new(1e-2);
+        let handles = Vec::from_iter((0..4).map(|i| {
+            world.add_body(AffineBody {
+                state: at(Vector3::new(2. * i as f64, 0., 0.)),
+                velocity: still(),
+                forces: still(),
+                motion: Motion::Static,
+                mesh: mesh.clone(),
+            })
+        }));
+        assert_eq!(mesh.edges().len(), 18);
+        assert!(world.remove_body(handles[1]).is_some());
+        assert!(world.remove_body(handles[1]).is_none());
+        assert_eq!(world.len(), 3);
+        assert_eq!(world.body(handles[2]).unwrap().state.translation.x, 4.);
+        assert_eq!(world.body(handles[3]).unwrap().state.translation.x, 6.);
+        let report = world.step(0.01).unwrap();
+        assert_eq!(report.isles, 0);
+        assert_eq!(report.body_pairs, 0);
+        assert_eq!(report.time_of_impact, 1.);
+    }
+
+    #[test]
+    fn cube_rests_on_floor() {
+        let (mesh, moments) = cube();
+        let mut world = World::new(1e-2);
+        world.friction = 0.5;
+        let floor = floor();
+        assert!(matches!(floor.moments(), Err(MeshError::Open(_))));
+        let computed = mesh.moments().unwrap();
+        assert!((computed.v - moments.v).abs() < 1e-12);
+        assert!((computed.zz - moments.zz).abs() < 1e-12);
+        world.add_body(AffineBody {
+            state: at(Vector3::zeros()),
+            velocity: still(),
+            forces: still(),
+            motion: Motion::Static,
+            mesh: floor,
+        });
+        let cube = world.add_body(AffineBody {
+            state: at(Vector3::new(0., 0., 0.6)),
+            velocity: AffineTransform {
+                translation: Vector3::new(0.5, 0., 0.),
+                ..still()
+            },
+            forces: AffineTransform {
+                translation: Vector3::new(0., 0., -9.8),
+                ..still()
+            },
+            motion: Motion::Dynamic {
+                moments,
+                density: 1.,
+            },
+            mesh,
+        });
+        let mut touched = false;
+        for _ in 0..100 {
+            let report = world.step(0.01).unwrap();
+            assert!(report.converged);
+            assert!(report.drift < 1e-6);
+            assert!(report.time_of_impact > 0. && report.time_of_impact <= 1.);
+            let body = world.body(cube).unwrap();
+            let lowest = body
+                .mesh
+                .vertices()
+                .iter()
+                .map(|x| body.state.transform_point(x).z)
+                .fold(f64::INFINITY, f64::min);
+            assert!(lowest > 0.);
+            touched |= report.contacts > 0;
+        }
+        assert!(touched);
+        let body = world.body(cube).unwrap();
+        assert!(body.state.translation.z < 0.5 + 1e-2);
+        //  Friction has stopped the sliding.
+        assert!(body.velocity.translation.norm() < 1e-2);
+        assert!(world.stiffness().is_some());
+    }
+    #[test]
+    fn pair_friction_overrides_friction() {
+        let (mesh, moments) = cube();
+        let mut world = World::new(1e-2);
+        world.friction = 0.5;
+        let floor = world.add_body(AffineBody {
+            state: at(Vector3::zeros()),
+            velocity: still(),
+            forces: still(),
+            motion: Motion::Static,
+            mesh: floor(),
+        });
+        let cubes = [-2., 2.].map(|y| {
+            world.add_body(AffineBody {
+                state: at(Vector3::new(0., y, 0.5 + 5e-3)),
+                velocity: AffineTransform {
+                    translation: Vector3::new(0.5, 0., 0.),
+                    ..still()
+                },
+                forces: AffineTransform {
+                    translation: Vector3::new(0., 0., -9.8),
+                    ..still()
+                },
+                motion: Motion::Dynamic {
+                    moments: moments.clone(),
+                    density: 1.,
+                },
+                mesh: mesh.clone(),
+            })
+        });
+        world.set_pair_friction(cubes[1], floor, Some(0.));
+        assert_eq!(world.pair_friction(floor, cubes[1]), 0.);
+        assert_eq!(world.pair_friction(floor, cubes[0]), 0.5);
+        for _ in 0..50 {
+            assert!(world.step(0.01).unwrap().converged);
+        }
+        let speed = |cube| world.body(cube).unwrap().velocity.translation.x;
+        assert!(speed(cubes[0]) < 1e-2);
+        assert!(speed(cubes[1]) > 0.45);
+        world.remove_body(cubes[1]);
+        world.set_pair_friction(floor, cubes[0], Some(0.2));
+        world.set_pair_friction(floor, cubes[0], None);
+        assert_eq!(world.pair_friction(cubes[0], floor), 0.5);
+    }
+
+    #[test]
+    fn blocked_kinematic_bodies_reject_the_step() {
+        let (mesh, moments) = cube();
+        let mut world = World::new(1e-2);
+        world.add_body(AffineBody {
+            state: at(Vector3::zeros()),
+            velocity: still(),
+            forces: still(),
+            motion: Motion::Static,
+            mesh: floor(),
+        });
+        let resting = world.add_body(AffineBody {
+            state: at(Vector3::new(0., 0., 0.5 + 5e-3)),
+            velocity: still(),
+            forces: still(),
+            motion: Motion::Dynamic {
+                moments,
+                density: 1.,
+            },
+            mesh: mesh.clone(),
+        });
+        //  Pressed down onto the resting cube, and then past the floor.
+        let press = world.add_body(AffineBody {
+            state: at(Vector3::new(0., 0., 1.5 + 1e-2)),
+            velocity: AffineTransform {
+                translation: Vector3::new(0., 0., -150.),
+                ..still()
+            },
+            forces: still(),
+            motion: Motion::Kinematic,
+            mesh,
+        });
+        let before = Vec::from_iter(world.bodies().map(|(_, body)| body.clone()));
+        assert_eq!(world.step(0.01), Err(StepError::Blocked(vec![press])));
+        assert!(world.bodies().map(|(_, body)| body).eq(&before));
+        //  Slowed down enough to only press it a little.
+        world.body_mut(press).unwrap().velocity.translation.z = -0.5;
+        world.step(0.01).unwrap();
+        let press = world.body(press).unwrap();
+        assert!((press.state.translation.z - (1.5 + 1e-2 - 5e-3)).abs() < 1e-12);
+        assert!(world.body(resting).unwrap().state.translation.z < 0.5 + 5e-3);
+    }
+}